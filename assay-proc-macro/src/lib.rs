@@ -6,37 +6,228 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use proc_macro::TokenStream;
+use proc_macro::{Span, TokenStream};
+use proc_macro2::{
+  Delimiter, Punct, Spacing, Span as Span2, TokenStream as TokenStream2, TokenTree,
+};
 use quote::quote;
+use std::{
+  env, fs,
+  path::{Component, Path, PathBuf},
+};
 use syn::{
-  parse::{Parse, ParseStream},
-  parse_macro_input, Expr, ExprArray, ExprLit, ExprTuple, Ident, ItemFn, Lit, Result, Token,
+  bracketed,
+  parse::{Parse, ParseStream, Parser},
+  parse_macro_input,
+  punctuated::Punctuated,
+  Error, Expr, ExprArray, ExprLit, ExprTuple, FnArg, Ident, ItemFn, Lit, LitInt, LitStr, Pat,
+  PatIdent, PatType, Result, Signature, Token, Type, TypeReference,
 };
+use toml::{Table, Value};
 
 struct AssayAttribute {
-  include: Option<Vec<String>>,
+  include: Option<Vec<Include>>,
+  include_readonly: bool,
+  include_no_overwrite: bool,
+  include_as_ext: Vec<Include>,
+  link: Option<Vec<(String, Option<String>)>>,
+  include_dir: Option<Vec<(String, Option<String>)>>,
+  create: Vec<(String, String)>,
   ignore: bool,
+  ignore_reason: Option<String>,
   should_panic: bool,
+  panic_expected: Option<String>,
   env: Option<Vec<(String, String)>>,
+  env_expand: bool,
+  env_file: Option<String>,
+  env_remove: Vec<String>,
   setup: Option<Expr>,
   teardown: Option<Expr>,
+  mock_time: bool,
+  thread_stack_size: Option<usize>,
+  rlimit_nofile: Option<u64>,
+  max_memory: Option<usize>,
+  thread_name: Option<String>,
+  fixture: Option<Type>,
+  fixture_binding: Option<Ident>,
+  require: Option<Expr>,
+  skip_on: Vec<String>,
+  path: Option<Vec<String>>,
+  bench: Option<Bench>,
+  args: Option<Vec<String>>,
+  flags: Option<Vec<Ident>>,
+  for_each_file: Option<LitStr>,
+  impls: Vec<Type>,
+  name_separator: Option<String>,
+  forbid_stderr: Option<Vec<String>>,
+  inline: Option<Span2>,
+  on_failure: Option<Expr>,
+  inspect_output: Option<Expr>,
+  stdout_golden: Option<String>,
+  sorted_asserts: bool,
+  strict_isolation: bool,
+  keep_on_failure: bool,
+  bindings: Vec<(Ident, Expr)>,
+}
+
+/// How many times to run a benchmarked test's body and how slow it may be
+struct Bench {
+  iterations: usize,
+  max_median: Option<String>,
+}
+
+/// A file to copy into the test's temp directory
+struct Include {
+  source: String,
+  dest: Option<String>,
+  readonly: bool,
 }
 
 impl Parse for AssayAttribute {
   fn parse(input: ParseStream) -> Result<Self> {
+    let tokens = flatten_config(input.parse()?)?;
+    Self::parse_fields.parse2(tokens)
+  }
+}
+
+/// Replace a `config = { key: value, .. }` block with the `key = value, ..`
+/// pairs it contains so that both styles parse the same way
+fn flatten_config(tokens: TokenStream2) -> Result<TokenStream2> {
+  let tokens = tokens.into_iter().collect::<Vec<_>>();
+  let mut flattened = Vec::new();
+  let mut i = 0;
+  while i < tokens.len() {
+    let at_key = flattened.is_empty() || ends_with_comma(&flattened);
+    match &tokens[i] {
+      TokenTree::Ident(ident) if at_key && ident == "config" => {
+        let group = match (tokens.get(i + 1), tokens.get(i + 2)) {
+          (Some(TokenTree::Punct(eq)), Some(TokenTree::Group(group)))
+            if eq.as_char() == '=' && group.delimiter() == Delimiter::Brace =>
+          {
+            group
+          }
+          _ => {
+            return Err(Error::new(
+              ident.span(),
+              "expected `config = { key: value, .. }`",
+            ))
+          }
+        };
+        let mut at_key = true;
+        let mut inner = group.stream().into_iter().peekable();
+        while let Some(token) = inner.next() {
+          match &token {
+            TokenTree::Ident(_) if at_key => {
+              flattened.push(token);
+              if let Some(TokenTree::Punct(colon)) = inner.peek() {
+                if colon.as_char() == ':' && colon.spacing() == Spacing::Alone {
+                  let mut eq = Punct::new('=', Spacing::Alone);
+                  eq.set_span(colon.span());
+                  flattened.push(eq.into());
+                  inner.next();
+                }
+              }
+              at_key = false;
+            }
+            TokenTree::Punct(p) if p.as_char() == ',' => {
+              flattened.push(token);
+              at_key = true;
+            }
+            _ => {
+              flattened.push(token);
+              at_key = false;
+            }
+          }
+        }
+        if !ends_with_comma(&flattened) {
+          flattened.push(Punct::new(',', Spacing::Alone).into());
+        }
+        i += 3;
+      }
+      // The block already ended with a comma
+      TokenTree::Punct(p) if p.as_char() == ',' && ends_with_comma(&flattened) => i += 1,
+      token => {
+        flattened.push(token.clone());
+        i += 1;
+      }
+    }
+  }
+  Ok(flattened.into_iter().collect())
+}
+
+fn ends_with_comma(tokens: &[TokenTree]) -> bool {
+  matches!(tokens.last(), Some(TokenTree::Punct(p)) if p.as_char() == ',')
+}
+
+/// Whether `name` appears anywhere in `tokens`, including inside groups
+fn mentions(tokens: TokenStream2, name: &str) -> bool {
+  tokens.into_iter().any(|token| match token {
+    TokenTree::Ident(ident) => ident == name,
+    TokenTree::Group(group) => mentions(group.stream(), name),
+    _ => false,
+  })
+}
+
+impl AssayAttribute {
+  fn parse_fields(input: ParseStream) -> Result<Self> {
     let mut include = None;
+    let mut include_readonly = false;
+    let mut include_no_overwrite = false;
+    let mut include_as_ext = Vec::new();
+    let mut link = None;
+    let mut include_dir = None;
+    let mut create = Vec::new();
+    let mut sorted_asserts = false;
+    let mut strict_isolation = false;
+    let mut keep_on_failure = false;
+    let mut env_expand = false;
+    let mut env_file = None;
+    let mut env_remove = Vec::new();
     let mut ignore = false;
+    let mut ignore_reason = None;
     let mut should_panic = false;
+    let mut panic_expected = None;
     let mut env = None;
     let mut setup = None;
     let mut teardown = None;
+    let mut mock_time = false;
+    let mut thread_stack_size = None;
+    let mut rlimit_nofile = None;
+    let mut max_memory = None;
+    let mut thread_name = None;
+    let mut fixture = None;
+    let mut require = None;
+    let mut skip_on = Vec::new();
+    let mut path = None;
+    let mut bench = false;
+    let mut args = None;
+    let mut flags = None;
+    let mut for_each_file = None;
+    let mut impls = Vec::new();
+    let mut name_separator: Option<LitStr> = None;
+    let mut forbid_stderr = None;
+    let mut inline = None;
+    let mut on_failure = None;
+    let mut inspect_output = None;
+    let mut stdout_golden = None;
+    let mut iterations = None;
+    let mut max_median = None;
+    let mut bindings = Vec::new();
 
-    while input.peek(Ident) || {
+    while input.peek(Ident) || input.peek(Token![let]) || {
       if input.peek(Token![,]) {
         let _: Token![,] = input.parse()?;
       }
-      input.peek(Ident)
+      input.peek(Ident) || input.peek(Token![let])
     } {
+      // `let name = expr` gives the body a value made before it runs
+      if input.peek(Token![let]) {
+        let _: Token![let] = input.parse()?;
+        let name: Ident = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        bindings.push((name, input.parse()?));
+        continue;
+      }
       let ident: Ident = input.parse()?;
       match ident.to_string().as_str() {
         "include" => {
@@ -50,40 +241,441 @@ impl Parse for AssayAttribute {
                 Expr::Lit(ExprLit {
                   lit: Lit::Str(lit_str),
                   ..
-                }) => Some(lit_str.value()),
+                }) => Some(Include {
+                  source: lit_str.value(),
+                  dest: None,
+                  readonly: false,
+                }),
+                // Either `(source, dest)`, `(source, ro)`, or `(source, dest, ro)`
+                Expr::Tuple(ExprTuple { elems, .. }) => {
+                  let mut elems = elems.into_iter();
+                  let source = match elems.next() {
+                    Some(Expr::Lit(ExprLit {
+                      lit: Lit::Str(source),
+                      ..
+                    })) => source.value(),
+                    _ => return None,
+                  };
+                  let mut include = Include {
+                    source,
+                    dest: None,
+                    readonly: false,
+                  };
+                  for elem in elems {
+                    match elem {
+                      Expr::Lit(ExprLit {
+                        lit: Lit::Str(dest),
+                        ..
+                      }) if include.dest.is_none() => include.dest = Some(dest.value()),
+                      Expr::Path(path) if path.path.is_ident("ro") => include.readonly = true,
+                      _ => return None,
+                    }
+                  }
+                  Some(include)
+                }
                 _ => None,
               })
               .collect(),
           );
         }
-        "should_panic" => should_panic = true,
-        "ignore" => ignore = true,
+        "link" => {
+          let _: Token![=] = input.parse()?;
+          link = Some(sources_and_dests(input.parse()?));
+        }
+        "include_dir" => {
+          let _: Token![=] = input.parse()?;
+          include_dir = Some(sources_and_dests(input.parse()?));
+        }
+        "include_as_ext" => {
+          let _: Token![=] = input.parse()?;
+          let array: ExprArray = input.parse()?;
+          let mut renamed = Vec::new();
+          for e in array.elems {
+            let Expr::Tuple(ExprTuple { elems, .. }) = &e else {
+              return Err(Error::new_spanned(e, "expected a `(source, extension)` pair"));
+            };
+            let mut parts = elems.iter();
+            let (
+              Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(source),
+                ..
+              })),
+              Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(extension),
+                ..
+              })),
+              None,
+            ) = (parts.next(), parts.next(), parts.next())
+            else {
+              return Err(Error::new_spanned(e, "expected a `(source, extension)` pair"));
+            };
+            // Goes where the file would have gone with only its extension
+            // changed, dropping the root of absolute paths like `include` does
+            let dest = Path::new(&source.value())
+              .with_extension(extension.value().trim_start_matches('.'))
+              .components()
+              .filter(|c| *c != Component::RootDir)
+              .collect::<PathBuf>();
+            renamed.push(Include {
+              source: source.value(),
+              dest: Some(dest.to_string_lossy().into_owned()),
+              readonly: false,
+            });
+          }
+          include_as_ext = renamed;
+        }
+        "create" => {
+          let _: Token![=] = input.parse()?;
+          let array: ExprArray = input.parse()?;
+          for e in array.elems {
+            let Expr::Tuple(ExprTuple { elems, .. }) = &e else {
+              return Err(Error::new_spanned(e, "expected a `(path, contents)` pair"));
+            };
+            let mut parts = elems.iter();
+            let (
+              Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(path),
+                ..
+              })),
+              Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(contents),
+                ..
+              })),
+              None,
+            ) = (parts.next(), parts.next(), parts.next())
+            else {
+              return Err(Error::new_spanned(e, "expected a `(path, contents)` pair"));
+            };
+            create.push((path.value(), contents.value()));
+          }
+        }
+        "include_readonly" => include_readonly = true,
+        "include_no_overwrite" => include_no_overwrite = true,
+        "sorted_asserts" => sorted_asserts = true,
+        "strict_isolation" if should_panic => {
+          return Err(Error::new(
+            ident.span(),
+            "`strict_isolation` cannot be used with `should_panic` as finding leftover state would pass",
+          ))
+        }
+        "strict_isolation" => strict_isolation = true,
+        "keep_on_failure" => keep_on_failure = true,
+        "env_expand" => env_expand = true,
+        "should_panic" if require.is_some() => {
+          return Err(Error::new(
+            ident.span(),
+            "`should_panic` cannot be used with `require` as a skipped test would fail",
+          ))
+        }
+        "should_panic" if inspect_output.is_some() => {
+          return Err(Error::new(
+            ident.span(),
+            "`should_panic` cannot be used with `inspect_output` as it only runs after a passing test",
+          ))
+        }
+        "should_panic" if stdout_golden.is_some() => {
+          return Err(Error::new(
+            ident.span(),
+            "`should_panic` cannot be used with `stdout_golden` as it only runs after a passing test",
+          ))
+        }
+        "should_panic" if strict_isolation => {
+          return Err(Error::new(
+            ident.span(),
+            "`should_panic` cannot be used with `strict_isolation` as finding leftover state would pass",
+          ))
+        }
+        "should_panic" if !skip_on.is_empty() => {
+          return Err(Error::new(
+            ident.span(),
+            "`should_panic` cannot be used with `skip_on` as a skipped test would fail",
+          ))
+        }
+        "should_panic" => {
+          // Optionally followed by text the panic message has to contain
+          if input.peek(Token![=]) {
+            let _: Token![=] = input.parse()?;
+            let expected: LitStr = input.parse()?;
+            panic_expected = Some(expected.value());
+          }
+          should_panic = true;
+        }
+        "ignore" => {
+          // Optionally followed by why, which `cargo test` shows
+          if input.peek(Token![=]) {
+            let _: Token![=] = input.parse()?;
+            let reason: LitStr = input.parse()?;
+            ignore_reason = Some(reason.value());
+          }
+          ignore = true;
+        }
+        "mock_time" => mock_time = true,
+        "inline" => inline = Some(ident.span()),
+        "bench" => bench = true,
+        "iterations" => {
+          let _: Token![=] = input.parse()?;
+          let n: LitInt = input.parse()?;
+          iterations = Some(n.base10_parse()?);
+        }
+        "max_median" => {
+          let _: Token![=] = input.parse()?;
+          let duration: LitStr = input.parse()?;
+          max_median = Some(duration.value());
+        }
         "env" => {
           let _: Token![=] = input.parse()?;
           let array: ExprArray = input.parse()?;
-          env = Some(
+          let mut vars = Vec::new();
+          for e in array.elems {
+            if let Expr::Tuple(ExprTuple { elems, .. }) = e {
+              if let (
+                Expr::Lit(ExprLit {
+                  lit: Lit::Str(lit_1),
+                  ..
+                }),
+                Expr::Lit(ExprLit {
+                  lit: Lit::Str(lit_2),
+                  ..
+                }),
+              ) = (&elems[0], &elems[1])
+              {
+                // `std::env::set_var` panics on these at runtime so catch them now
+                if let Some(message) = invalid_env_key(&lit_1.value()) {
+                  return Err(Error::new(lit_1.span(), message));
+                }
+                if lit_2.value().contains('\0') {
+                  return Err(Error::new(lit_2.span(), INVALID_ENV_VALUE));
+                }
+                vars.push((lit_1.value(), lit_2.value()));
+              }
+            }
+          }
+          env = Some(vars);
+        }
+        "env_remove" => {
+          let _: Token![=] = input.parse()?;
+          let array: ExprArray = input.parse()?;
+          if array.elems.is_empty() {
+            return Err(Error::new_spanned(
+              array,
+              "`env_remove` needs the name of at least one env var",
+            ));
+          }
+          for e in array.elems {
+            match e {
+              Expr::Lit(ExprLit {
+                lit: Lit::Str(key),
+                ..
+              }) => {
+                // `std::env::remove_var` panics on these at runtime too
+                if let Some(message) = invalid_env_key(&key.value()) {
+                  return Err(Error::new(key.span(), message));
+                }
+                env_remove.push(key.value());
+              }
+              e => return Err(Error::new_spanned(e, "expected the name of an env var")),
+            }
+          }
+        }
+        "env_file" => {
+          let _: Token![=] = input.parse()?;
+          let path: LitStr = input.parse()?;
+          env_file = Some(path.value());
+        }
+        "args" => {
+          let _: Token![=] = input.parse()?;
+          let array: ExprArray = input.parse()?;
+          let mut values = Vec::new();
+          for e in array.elems {
+            if let Expr::Lit(ExprLit {
+              lit: Lit::Str(lit_str),
+              ..
+            }) = e
+            {
+              // Arguments are joined with this to pass them to the test
+              if lit_str.value().contains('\x1f') {
+                return Err(Error::new(
+                  lit_str.span(),
+                  "args cannot contain the unit separator character `\\x1f`",
+                ));
+              }
+              values.push(lit_str.value());
+            }
+          }
+          args = Some(values);
+        }
+        "forbid_stderr" => {
+          // Optionally followed by text that's allowed to show up
+          let mut allowed = Vec::new();
+          if input.peek(Token![=]) {
+            let _: Token![=] = input.parse()?;
+            let array: ExprArray = input.parse()?;
+            for e in array.elems {
+              match e {
+                Expr::Lit(ExprLit {
+                  lit: Lit::Str(lit_str),
+                  ..
+                }) => allowed.push(lit_str.value()),
+                e => return Err(Error::new_spanned(e, "expected a string")),
+              }
+            }
+          }
+          forbid_stderr = Some(allowed);
+        }
+        "flags" => {
+          let _: Token![=] = input.parse()?;
+          let array: ExprArray = input.parse()?;
+          let mut idents = Vec::new();
+          for e in array.elems {
+            match e {
+              Expr::Path(path) if path.path.get_ident().is_some() => {
+                idents.push(path.path.get_ident().unwrap().clone())
+              }
+              e => return Err(Error::new_spanned(e, "expected the name of a flag")),
+            }
+          }
+          if idents.len() > 8 {
+            return Err(Error::new(
+              ident.span(),
+              "at most 8 flags are supported as every combination of them is a separate test",
+            ));
+          }
+          flags = Some(idents);
+        }
+        "for_each_file" => {
+          let _: Token![=] = input.parse()?;
+          for_each_file = Some(input.parse()?);
+        }
+        "impls" => {
+          let _: Token![=] = input.parse()?;
+          let types;
+          bracketed!(types in input);
+          impls = Punctuated::<Type, Token![,]>::parse_terminated(&types)?
+            .into_iter()
+            .collect();
+          if impls.is_empty() {
+            return Err(Error::new(ident.span(), "`impls` needs at least one type"));
+          }
+        }
+        "name_separator" => {
+          let _: Token![=] = input.parse()?;
+          let separator: LitStr = input.parse()?;
+          let value = separator.value();
+          if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::new(
+              separator.span(),
+              "`name_separator` can only contain letters, numbers, and `_` so that test names stay valid identifiers",
+            ));
+          }
+          name_separator = Some(separator);
+        }
+        "path" => {
+          let _: Token![=] = input.parse()?;
+          let array: ExprArray = input.parse()?;
+          path = Some(
             array
               .elems
               .into_iter()
               .filter_map(|e| match e {
-                Expr::Tuple(ExprTuple { elems, .. }) => match (&elems[0], &elems[1]) {
-                  (
-                    Expr::Lit(ExprLit {
-                      lit: Lit::Str(lit_1),
-                      ..
-                    }),
-                    Expr::Lit(ExprLit {
-                      lit: Lit::Str(lit_2),
-                      ..
-                    }),
-                  ) => Some((lit_1.value(), lit_2.value())),
-                  _ => None,
-                },
+                Expr::Lit(ExprLit {
+                  lit: Lit::Str(lit_str),
+                  ..
+                }) => Some(lit_str.value()),
                 _ => None,
               })
               .collect(),
           );
         }
+        "rlimit_nofile" => {
+          let _: Token![=] = input.parse()?;
+          let limit: LitInt = input.parse()?;
+          rlimit_nofile = Some(limit.base10_parse()?);
+        }
+        "max_memory" => {
+          let _: Token![=] = input.parse()?;
+          let size: LitStr = input.parse()?;
+          max_memory = Some(parse_size(&size.value()).map_err(|e| Error::new(size.span(), e))?);
+        }
+        "thread_stack_size" => {
+          let _: Token![=] = input.parse()?;
+          let size: LitStr = input.parse()?;
+          thread_stack_size =
+            Some(parse_size(&size.value()).map_err(|e| Error::new(size.span(), e))?);
+        }
+        "thread_name" => {
+          let _: Token![=] = input.parse()?;
+          let name: LitStr = input.parse()?;
+          thread_name = Some(name.value());
+        }
+        "fixture" => {
+          let _: Token![=] = input.parse()?;
+          fixture = Some(input.parse()?);
+        }
+        "on_failure" => {
+          let _: Token![=] = input.parse()?;
+          on_failure = Some(input.parse()?);
+        }
+        "inspect_output" => {
+          if should_panic {
+            return Err(Error::new(
+              ident.span(),
+              "`inspect_output` cannot be used with `should_panic` as it only runs after a passing test",
+            ));
+          }
+          let _: Token![=] = input.parse()?;
+          inspect_output = Some(input.parse()?);
+        }
+        "stdout_golden" => {
+          if should_panic {
+            return Err(Error::new(
+              ident.span(),
+              "`stdout_golden` cannot be used with `should_panic` as it only runs after a passing test",
+            ));
+          }
+          let _: Token![=] = input.parse()?;
+          let path: LitStr = input.parse()?;
+          stdout_golden = Some(path.value());
+        }
+        "require" => {
+          if should_panic {
+            return Err(Error::new(
+              ident.span(),
+              "`require` cannot be used with `should_panic` as a skipped test would fail",
+            ));
+          }
+          let _: Token![=] = input.parse()?;
+          require = Some(input.parse()?);
+        }
+        "skip_on" => {
+          if should_panic {
+            return Err(Error::new(
+              ident.span(),
+              "`skip_on` cannot be used with `should_panic` as a skipped test would fail",
+            ));
+          }
+          let _: Token![=] = input.parse()?;
+          // Either a single OS or a list of them
+          let oses = if input.peek(LitStr) {
+            vec![input.parse::<LitStr>()?]
+          } else {
+            let array: ExprArray = input.parse()?;
+            array
+              .elems
+              .into_iter()
+              .map(|e| match e {
+                Expr::Lit(ExprLit {
+                  lit: Lit::Str(lit_str),
+                  ..
+                }) => Ok(lit_str),
+                e => Err(Error::new_spanned(
+                  e,
+                  "`skip_on` expects OS names like \"windows\"",
+                )),
+              })
+              .collect::<Result<_>>()?
+          };
+          skip_on = oses.iter().map(LitStr::value).collect();
+        }
         val @ "setup" | val @ "teardown" => {
           let _: Token![=] = input.parse()?;
           let x = input.parse()?;
@@ -97,53 +689,730 @@ impl Parse for AssayAttribute {
       }
     }
 
+    if let (Some(separator), None) = (&name_separator, &flags) {
+      return Err(Error::new(
+        separator.span(),
+        "`name_separator` only applies to the tests generated by `flags`",
+      ));
+    }
+
     Ok(AssayAttribute {
       include,
+      include_readonly,
+      include_no_overwrite,
+      include_as_ext,
+      link,
+      include_dir,
+      create,
       ignore,
+      ignore_reason,
       should_panic,
+      panic_expected,
       env,
       setup,
       teardown,
+      mock_time,
+      thread_stack_size,
+      rlimit_nofile,
+      max_memory,
+      thread_name,
+      fixture,
+      fixture_binding: None,
+      require,
+      skip_on,
+      path,
+      args,
+      flags,
+      for_each_file,
+      impls,
+      name_separator: name_separator.map(|separator| separator.value()),
+      forbid_stderr,
+      inline,
+      on_failure,
+      inspect_output,
+      stdout_golden,
+      sorted_asserts,
+      strict_isolation,
+      keep_on_failure,
+      env_expand,
+      env_file,
+      env_remove,
+      bindings,
+      bench: (bench || iterations.is_some() || max_median.is_some()).then(|| Bench {
+        iterations: iterations.unwrap_or(100),
+        max_median,
+      }),
     })
   }
 }
 
+impl AssayAttribute {
+  /// Take a `name: &mut T` parameter off of the test as its fixture, the same
+  /// as `fixture = T` but bound to `name` instead of `fixture`
+  fn take_fixture_param(&mut self, sig: &mut Signature) -> Result<()> {
+    let Some(param) = sig.inputs.first() else {
+      return Ok(());
+    };
+    let fixture = match param {
+      FnArg::Typed(PatType { pat, ty, .. }) if sig.inputs.len() == 1 => match (&**pat, &**ty) {
+        (
+          Pat::Ident(PatIdent { ident, .. }),
+          Type::Reference(TypeReference {
+            mutability: Some(_),
+            elem,
+            ..
+          }),
+        ) => Some((ident.clone(), (**elem).clone())),
+        _ => None,
+      },
+      _ => None,
+    };
+    let Some((binding, ty)) = fixture else {
+      return Err(Error::new_spanned(
+        &sig.inputs,
+        "`#[assay]` tests can only take a single `name: &mut T` parameter where `T` is a fixture",
+      ));
+    };
+    if self.fixture.is_some() {
+      return Err(Error::new_spanned(
+        param,
+        "the fixture is already given with `fixture`, use only one of the two",
+      ));
+    }
+    self.fixture = Some(ty);
+    self.fixture_binding = Some(binding);
+    sig.inputs.clear();
+    Ok(())
+  }
+
+  /// Make sure an `inline` test doesn't ask for anything that needs its own
+  /// process or temporary directory
+  fn check_inline(&self) -> Result<()> {
+    let Some(span) = self.inline else {
+      return Ok(());
+    };
+    let conflicts = [
+      ("include", self.include.is_some()),
+      ("include_as_ext", !self.include_as_ext.is_empty()),
+      ("link", self.link.is_some()),
+      ("include_dir", self.include_dir.is_some()),
+      ("create", !self.create.is_empty()),
+      ("env", self.env.is_some()),
+      ("env_file", self.env_file.is_some()),
+      ("env_remove", !self.env_remove.is_empty()),
+      ("path", self.path.is_some()),
+      ("args", self.args.is_some()),
+      ("setup", self.setup.is_some()),
+      ("teardown", self.teardown.is_some()),
+      ("forbid_stderr", self.forbid_stderr.is_some()),
+      ("on_failure", self.on_failure.is_some()),
+      ("inspect_output", self.inspect_output.is_some()),
+      ("stdout_golden", self.stdout_golden.is_some()),
+      ("rlimit_nofile", self.rlimit_nofile.is_some()),
+      ("max_memory", self.max_memory.is_some()),
+      ("keep_on_failure", self.keep_on_failure),
+      ("mock_time", self.mock_time),
+    ];
+    match conflicts.iter().find(|(_, set)| *set) {
+      Some((name, _)) => Err(Error::new(
+        span,
+        format!(
+          "`inline` tests run in the same process and directory as every other test so they cannot use `{name}` (which may be set in assay.toml)"
+        ),
+      )),
+      None => Ok(()),
+    }
+  }
+
+  /// Fill in anything not set on the test itself with the defaults from an
+  /// `assay.toml` next to the crate's `Cargo.toml`, returning the path to the
+  /// file if there is one
+  fn apply_config(&mut self) -> Result<Option<String>> {
+    let Some(dir) = env::var_os("CARGO_MANIFEST_DIR") else {
+      return Ok(None);
+    };
+    let path = Path::new(&dir).join("assay.toml");
+    let Ok(contents) = fs::read_to_string(&path) else {
+      return Ok(None);
+    };
+    let error = |message: String| {
+      Error::new(
+        Span::call_site().into(),
+        format!("{}: {message}", path.display()),
+      )
+    };
+    let table = contents
+      .parse::<Table>()
+      .map_err(|e| error(e.to_string()))?;
+
+    for (key, value) in table {
+      match key.as_str() {
+        "include" => {
+          let include = config_strings(&key, value)
+            .map_err(error)?
+            .into_iter()
+            .map(|source| Include {
+              source,
+              dest: None,
+              readonly: false,
+            })
+            .collect();
+          self.include.get_or_insert(include);
+        }
+        "include_readonly" => self.include_readonly |= config_bool(&key, value).map_err(error)?,
+        "include_no_overwrite" => {
+          self.include_no_overwrite |= config_bool(&key, value).map_err(error)?
+        }
+        "mock_time" => self.mock_time |= config_bool(&key, value).map_err(error)?,
+        "env" => {
+          let env = match value {
+            Value::Table(table) => table
+              .into_iter()
+              .map(|(k, v)| {
+                if let Some(message) = invalid_env_key(&k) {
+                  return Err(format!("`env.{k}`: {message}"));
+                }
+                match v {
+                  Value::String(v) if v.contains('\0') => {
+                    Err(format!("`env.{k}`: {INVALID_ENV_VALUE}"))
+                  }
+                  Value::String(v) => Ok((k, v)),
+                  _ => Err(format!("`env.{k}` must be a string")),
+                }
+              })
+              .collect::<std::result::Result<Vec<_>, _>>()
+              .map_err(error)?,
+            _ => return Err(error("`env` must be a table of strings".into())),
+          };
+          self.env.get_or_insert(env);
+        }
+        "thread_stack_size" => {
+          let size = parse_size(&config_string(&key, value).map_err(error)?).map_err(error)?;
+          self.thread_stack_size.get_or_insert(size);
+        }
+        "thread_name" => {
+          let name = config_string(&key, value).map_err(error)?;
+          self.thread_name.get_or_insert(name);
+        }
+        key => {
+          return Err(error(format!(
+            "unknown key `{key}`, expected one of include, include_readonly, include_no_overwrite, env, mock_time, thread_stack_size, or thread_name"
+          )))
+        }
+      }
+    }
+
+    Ok(Some(path.display().to_string()))
+  }
+}
+
+const INVALID_ENV_VALUE: &str = "env var values cannot contain a NUL character";
+
+/// Why `key` can't be used as the name of an env var, if it can't
+fn invalid_env_key(key: &str) -> Option<&'static str> {
+  if key.is_empty() {
+    Some("env var names cannot be empty")
+  } else if key.contains('=') {
+    Some("env var names cannot contain `=`")
+  } else if key.contains('\0') {
+    Some("env var names cannot contain a NUL character")
+  } else {
+    None
+  }
+}
+
+fn config_bool(key: &str, value: Value) -> std::result::Result<bool, String> {
+  match value {
+    Value::Boolean(b) => Ok(b),
+    _ => Err(format!("`{key}` must be a boolean")),
+  }
+}
+
+fn config_string(key: &str, value: Value) -> std::result::Result<String, String> {
+  match value {
+    Value::String(s) => Ok(s),
+    _ => Err(format!("`{key}` must be a string")),
+  }
+}
+
+fn config_strings(key: &str, value: Value) -> std::result::Result<Vec<String>, String> {
+  match value {
+    Value::Array(array) => array
+      .into_iter()
+      .map(|v| config_string(key, v).map_err(|_| format!("`{key}` must be an array of strings")))
+      .collect(),
+    _ => Err(format!("`{key}` must be an array of strings")),
+  }
+}
+
+/// The `source` or `(source, dest)` paths in a list like `link` takes
+fn sources_and_dests(array: ExprArray) -> Vec<(String, Option<String>)> {
+  array
+    .elems
+    .into_iter()
+    .filter_map(|e| match e {
+      Expr::Lit(ExprLit {
+        lit: Lit::Str(source),
+        ..
+      }) => Some((source.value(), None)),
+      Expr::Tuple(ExprTuple { elems, .. }) if elems.len() == 2 => match (&elems[0], &elems[1]) {
+        (
+          Expr::Lit(ExprLit {
+            lit: Lit::Str(source),
+            ..
+          }),
+          Expr::Lit(ExprLit {
+            lit: Lit::Str(dest),
+            ..
+          }),
+        ) => Some((source.value(), Some(dest.value()))),
+        _ => None,
+      },
+      _ => None,
+    })
+    .collect()
+}
+
+/// Parse a human readable size like `8MiB` or `512KB` into a number of bytes
+fn parse_size(value: &str) -> std::result::Result<usize, String> {
+  let value = value.trim();
+  let (number, unit) = value.split_at(
+    value
+      .find(|c: char| !c.is_ascii_digit())
+      .unwrap_or(value.len()),
+  );
+  let multiplier = match unit.trim() {
+    "" | "B" => 1,
+    "KB" => 1_000,
+    "KiB" => 1 << 10,
+    "MB" => 1_000_000,
+    "MiB" => 1 << 20,
+    "GB" => 1_000_000_000,
+    "GiB" => 1 << 30,
+    unit => {
+      return Err(format!(
+        "unknown size unit `{unit}`, expected one of B, KB, KiB, MB, MiB, GB, or GiB"
+      ))
+    }
+  };
+  number
+    .parse::<usize>()
+    .ok()
+    .and_then(|n| n.checked_mul(multiplier))
+    .ok_or_else(|| "expected a size such as \"8MiB\"".to_string())
+}
+
 #[proc_macro_attribute]
 pub fn assay(attr: TokenStream, item: TokenStream) -> TokenStream {
-  let attr = parse_macro_input!(attr as AssayAttribute);
+  let mut attr = parse_macro_input!(attr as AssayAttribute);
+  let mut func = parse_macro_input!(item as ItemFn);
+  if let Err(e) = attr.take_fixture_param(&mut func.sig) {
+    return e.to_compile_error().into();
+  }
+  let config = match attr.apply_config() {
+    Ok(config) => config,
+    Err(e) => return e.to_compile_error().into(),
+  };
+  if let Err(e) = attr.check_inline() {
+    return e.to_compile_error().into();
+  }
+  // Depend on the config file so that changing it rebuilds the tests
+  let config = match config {
+    Some(path) => quote! { const _: &[u8] = include_bytes!(#path); },
+    None => quote! {},
+  };
+
+  let funcs = match &attr.for_each_file {
+    Some(pattern) => match for_each_file(pattern, &func) {
+      Ok(funcs) => funcs,
+      Err(e) => return e.to_compile_error().into(),
+    },
+    None => vec![func],
+  };
+  let funcs = if attr.impls.is_empty() {
+    funcs
+  } else {
+    match funcs
+      .iter()
+      .map(|func| for_each_impl(&attr.impls, func))
+      .collect::<Result<Vec<_>>>()
+    {
+      Ok(funcs) => funcs.into_iter().flatten().collect(),
+      Err(e) => return e.to_compile_error().into(),
+    }
+  };
+  let expanded = funcs
+    .into_iter()
+    .map(|func| expand_flags(&attr, &config, func));
+
+  // Hand the output tokens back to the compiler.
+  TokenStream::from(quote! { #(#expanded)* })
+}
+
+/// Make a copy of the test for every file matching `pattern`, with `input`
+/// bound to the file's path and `expected` to the same path with an
+/// `.expected` extension
+fn for_each_file(pattern: &LitStr, func: &ItemFn) -> Result<Vec<ItemFn>> {
+  let error = |message: String| Error::new(pattern.span(), message);
+  let dir = env::var_os("CARGO_MANIFEST_DIR")
+    .ok_or_else(|| error("`CARGO_MANIFEST_DIR` is not set".into()))?;
+  let value = pattern.value();
+  let (parent, file_pattern) = match value.rsplit_once('/') {
+    Some((parent, file_pattern)) => (Path::new(&dir).join(parent), file_pattern.to_string()),
+    None => (Path::new(&dir).to_owned(), value.clone()),
+  };
+  if parent.to_string_lossy().contains(['*', '?']) {
+    return Err(error(
+      "only the file name in `for_each_file` can contain `*` or `?`".into(),
+    ));
+  }
+  let entries =
+    fs::read_dir(&parent).map_err(|e| error(format!("cannot read `{}`: {e}", parent.display())))?;
+  let mut paths = entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.is_file())
+    .filter(|path| {
+      path
+        .file_name()
+        .is_some_and(|name| wildcard_match(&file_pattern, &name.to_string_lossy()))
+    })
+    .collect::<Vec<_>>();
+  if paths.is_empty() {
+    return Err(error(format!("no files match `{value}`")));
+  }
+  paths.sort();
+
+  let mut names = Vec::<String>::new();
+  let mut funcs = Vec::new();
+  for path in paths {
+    // Test names come from the file name without its extension, made into
+    // something that's valid in an identifier
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let suffix = stem
+      .chars()
+      .map(|c| {
+        if c.is_ascii_alphanumeric() {
+          c.to_ascii_lowercase()
+        } else {
+          '_'
+        }
+      })
+      .collect::<String>();
+    let name = format!("{}_{suffix}", func.sig.ident);
+    if names.contains(&name) {
+      return Err(error(format!(
+        "more than one file matching `{value}` would make a test named `{name}`"
+      )));
+    }
+    names.push(name.clone());
+
+    let input = path.to_string_lossy().into_owned();
+    let expected = path
+      .with_extension("expected")
+      .to_string_lossy()
+      .into_owned();
+    let mut func = func.clone();
+    func.sig.ident = Ident::new(&name, func.sig.ident.span());
+    let block = &func.block;
+    func.block = syn::parse_quote! {{
+      // Rebuild the test when the input changes
+      const _: &[u8] = include_bytes!(#input);
+      #[allow(unused_variables)]
+      let input = std::path::PathBuf::from(#input);
+      #[allow(unused_variables)]
+      let expected = std::path::PathBuf::from(#expected);
+      #block
+    }};
+    funcs.push(func);
+  }
+  Ok(funcs)
+}
+
+/// Make a copy of the test for every type in `impls`, with `Sut` as an alias
+/// for the type in each one
+fn for_each_impl(impls: &[Type], func: &ItemFn) -> Result<Vec<ItemFn>> {
+  let mut names = Vec::<String>::new();
+  let mut funcs = Vec::new();
+  for ty in impls {
+    // Tests are named after the last part of the type's path in snake case,
+    // leaving off any generic arguments
+    let ident = match ty {
+      Type::Path(path) if path.qself.is_none() => path.path.segments.last().map(|s| &s.ident),
+      _ => None,
+    }
+    .ok_or_else(|| Error::new_spanned(ty, "expected the name of a type"))?;
+    let name = format!("{}_{}", func.sig.ident, snake_case(&ident.to_string()));
+    if names.contains(&name) {
+      return Err(Error::new_spanned(
+        ty,
+        format!("more than one type in `impls` would make a test named `{name}`"),
+      ));
+    }
+    names.push(name.clone());
+
+    let mut func = func.clone();
+    func.sig.ident = Ident::new(&name, func.sig.ident.span());
+    let block = &func.block;
+    func.block = syn::parse_quote! {{
+      #[allow(dead_code)]
+      type Sut = #ty;
+      #block
+    }};
+    funcs.push(func);
+  }
+  Ok(funcs)
+}
 
-  let include = if let Some(include) = attr.include {
+/// Turn a type name like `BTreeStore` into `b_tree_store`
+fn snake_case(name: &str) -> String {
+  let chars = name.chars().collect::<Vec<_>>();
+  let mut out = String::new();
+  for (i, c) in chars.iter().enumerate() {
+    if c.is_uppercase() && i > 0 {
+      let after_lower = chars[i - 1].is_lowercase() || chars[i - 1].is_ascii_digit();
+      let ends_acronym =
+        chars[i - 1].is_uppercase() && chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+      if after_lower || ends_acronym {
+        out.push('_');
+      }
+    }
+    out.extend(c.to_lowercase());
+  }
+  out
+}
+
+/// Define the items given here and in the `assay` crate too, through the
+/// `__shared` macro, so that the two can't end up disagreeing
+macro_rules! shared {
+  ($($item:item)*) => {
+    $($item)*
+
+    #[doc(hidden)]
+    #[proc_macro]
+    pub fn __shared(_: TokenStream) -> TokenStream {
+      quote! { $($item)* }.into()
+    }
+  };
+}
+
+shared! {
+  /// Whether `name` matches `pattern` where `*` is any run of characters and
+  /// `?` is any single one. `include` matches files with this when the test
+  /// runs and `for_each_file` when it's compiled.
+  fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    // Whether the pattern so far matches the first `i` characters of the name
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+    for p in pattern {
+      if p == '*' {
+        for i in 1..=name.len() {
+          matches[i] |= matches[i - 1];
+        }
+      } else {
+        for i in (1..=name.len()).rev() {
+          matches[i] = matches[i - 1] && (p == '?' || p == name[i - 1]);
+        }
+        matches[0] = false;
+      }
+    }
+    matches[name.len()]
+  }
+}
+
+/// Generate the test, or one for every combination of its flags
+fn expand_flags(attr: &AssayAttribute, config: &TokenStream2, func: ItemFn) -> TokenStream2 {
+  match &attr.flags {
+    Some(flags) => {
+      // One test for every combination of flags being on or off, with each
+      // flag bound to a `bool` in the body
+      let mut tests = quote! {};
+      for subset in 0..1u64 << flags.len() {
+        let mut func = func.clone();
+        let enabled = |i: usize| subset & (1 << i) != 0;
+        let values = (0..flags.len()).map(enabled);
+        let names = flags
+          .iter()
+          .enumerate()
+          .filter(|(i, _)| enabled(*i))
+          .map(|(_, flag)| flag.to_string())
+          .collect::<Vec<_>>();
+        let separator = attr.name_separator.as_deref().unwrap_or("_");
+        let suffix = if names.is_empty() {
+          "none".to_string()
+        } else {
+          names.join(separator)
+        };
+        func.sig.ident = Ident::new(
+          &format!("{}{separator}{suffix}", func.sig.ident),
+          func.sig.ident.span(),
+        );
+        let block = &func.block;
+        func.block = syn::parse_quote! {{
+          #(let #flags: bool = #values;)*
+          #block
+        }};
+        let test = expand(attr, config, func);
+        tests = quote! {
+          #tests
+          #test
+        };
+      }
+      tests
+    }
+    None => expand(attr, config, func),
+  }
+}
+
+/// Generate a single test from a function and the attribute on it
+fn expand(attr: &AssayAttribute, config: &TokenStream2, func: ItemFn) -> TokenStream2 {
+  let keep_on_failure = attr.keep_on_failure;
+  let include = if attr.inline.is_some() {
+    quote! {}
+  } else if attr.include.is_some() || !attr.include_as_ext.is_empty() {
     let mut out = quote! {
-      let fs = assay::PrivateFS::new()?;
+      let mut fs = assay::PrivateFS::new()?;
+      fs.keep_on_failure(#keep_on_failure);
     };
-    for file in include {
-      out = quote! {
-        #out
-        fs.include(#file)?;
+    for Include {
+      source,
+      dest,
+      readonly,
+    } in attr.include.iter().flatten().chain(&attr.include_as_ext)
+    {
+      let readonly = *readonly || attr.include_readonly;
+      let no_overwrite = attr.include_no_overwrite;
+      out = match (dest, readonly) {
+        (dest, readonly) if no_overwrite => {
+          let dest = match dest {
+            Some(dest) => quote! { Some(#dest) },
+            None => quote! { None },
+          };
+          quote! {
+            #out
+            fs.include_with(#source, #dest, assay::IncludeOptions {
+              readonly: #readonly,
+              no_overwrite: true,
+            })?;
+          }
+        }
+        (Some(dest), false) => quote! {
+          #out
+          fs.include_as(#source, #dest)?;
+        },
+        (Some(dest), true) => quote! {
+          #out
+          fs.include_as_readonly(#source, #dest)?;
+        },
+        (None, false) => quote! {
+          #out
+          fs.include(#source)?;
+        },
+        (None, true) => quote! {
+          #out
+          fs.include_readonly(#source)?;
+        },
       };
     }
     out
   } else {
     quote! {
-      let fs = assay::PrivateFS::new()?;
+      let mut fs = assay::PrivateFS::new()?;
+      fs.keep_on_failure(#keep_on_failure);
     }
   };
-
-  let ignore = if attr.ignore {
-    quote! { #[ignore] }
+  let include = match &attr.include_dir {
+    Some(dirs) if attr.inline.is_none() => {
+      let dirs = dirs.iter().map(|(source, dest)| {
+        let dest = match dest {
+          Some(dest) => quote! { Some(#dest) },
+          None => quote! { None },
+        };
+        quote! { fs.include_dir_with(#source, #dest)?; }
+      });
+      quote! {
+        #include
+        #(#dirs)*
+      }
+    }
+    _ => include,
+  };
+  let include = match &attr.link {
+    Some(links) if attr.inline.is_none() => {
+      let links = links.iter().map(|(source, dest)| {
+        let dest = match dest {
+          Some(dest) => quote! { Some(#dest) },
+          None => quote! { None },
+        };
+        quote! { fs.link_with(#source, #dest)?; }
+      });
+      quote! {
+        #include
+        #(#links)*
+      }
+    }
+    _ => include,
+  };
+  // Written after everything else so they can replace an included file
+  let include = if attr.inline.is_none() {
+    let files = attr
+      .create
+      .iter()
+      .map(|(path, contents)| quote! { fs.create_file(#path, #contents)?; });
+    quote! {
+      #include
+      #(#files)*
+    }
   } else {
-    quote! {}
+    include
   };
 
-  let should_panic = if attr.should_panic {
-    quote! { #[should_panic] }
-  } else {
-    quote! {}
+  let ignore = match (&attr.ignore, &attr.ignore_reason) {
+    (true, Some(reason)) => quote! { #[ignore = #reason] },
+    (true, None) => quote! { #[ignore] },
+    (false, _) => quote! {},
   };
 
-  let env = if let Some(env) = attr.env {
+  let expect_panic = attr.should_panic;
+  let should_panic = match (&attr.should_panic, &attr.panic_expected) {
+    (true, Some(expected)) => quote! { #[should_panic(expected = #expected)] },
+    (true, None) => quote! { #[should_panic] },
+    (false, _) => quote! {},
+  };
+  // The child already checked that its panic message had the expected text in
+  // it, but the parent's message needs it too for the parent to pass. When it
+  // didn't, the parent only knows the child's test failed, so the child's
+  // output is shown for why
+  let expected_panic = attr.panic_expected.as_ref().map(|expected| {
+    quote! {
+      if stdout.contains(&format!("{name} - should panic ... ok")) {
+        assay::panic_replace();
+        panic!("ASSAY_PANIC_INTERNAL_MESSAGE\n{}", #expected)
+      }
+      if stdout.contains(&format!("{name} - should panic ... FAILED")) {
+        let stdout_line = format!("---- {name} stdout ----");
+        let output = stdout
+          .lines()
+          .skip_while(|line| line != &stdout_line)
+          .skip(1)
+          .take_while(|s| !s.starts_with("----") && !s.starts_with("failures:"))
+          .collect::<Vec<&str>>()
+          .join("\n");
+        println!("{}", output.trim());
+      }
+    }
+  });
+
+  let env = if let Some(env) = &attr.env {
     let mut out = quote! {};
     for (k, v) in env {
+      // Expanded one at a time so each value can use the ones set before it
+      let v = if attr.env_expand {
+        quote! { assay::expand_env(#v) }
+      } else {
+        quote! { #v }
+      };
       out = quote! {
         #out
         std::env::set_var(#k,#v);
@@ -153,36 +1422,362 @@ pub fn assay(attr: TokenStream, item: TokenStream) -> TokenStream {
   } else {
     quote! {}
   };
+  let env = match &attr.args {
+    Some(args) => {
+      let args = args.join("\x1f");
+      quote! {
+        #env
+        std::env::set_var("ASSAY_ARGS", #args);
+      }
+    }
+    None => env,
+  };
+  let env = match &attr.path {
+    Some(path) => quote! {
+      #env
+      assay::set_path(&[#(#path),*])?;
+    },
+    None => env,
+  };
 
-  let setup = match attr.setup {
-    Some(expr) => quote! { #expr; },
+  // Label errors from setup and teardown so they aren't mistaken for the body
+  let setup = match &attr.setup {
+    Some(expr) => quote! {
+      assay::eyre::WrapErr::wrap_err(
+        (|| -> assay::Result<()> { #expr; Ok(()) })(),
+        "setup failed",
+      )?;
+    },
+    None => quote! {},
+  };
+  let teardown = match &attr.teardown {
+    Some(expr) => quote! {
+      assay::eyre::WrapErr::wrap_err(
+        (|| -> assay::Result<()> { #expr; Ok(()) })(),
+        "teardown failed",
+      )?;
+    },
     None => quote! {},
   };
-  let teardown = match attr.teardown {
-    Some(expr) => quote! { #expr; },
+
+  let bindings = attr
+    .bindings
+    .iter()
+    .map(|(name, expr)| quote! { let #name = #expr; });
+  let bindings = quote! { #(#bindings)* };
+
+  let rlimit_nofile = match attr.rlimit_nofile {
+    Some(limit) => quote! { assay::set_rlimit_nofile(#limit)?; },
     None => quote! {},
   };
 
-  // Parse the function out into individual parts
-  let func = parse_macro_input!(item as ItemFn);
+  let mock_time = if attr.mock_time {
+    quote! { assay::install_mock_clock(); }
+  } else {
+    quote! {}
+  };
+  let paused = attr.mock_time;
+  let paused_check = if attr.mock_time {
+    quote! { assay::__mock_time_async!(); }
+  } else {
+    quote! {}
+  };
+  let thread_stack_size = match attr.thread_stack_size {
+    Some(size) => quote! { Some(#size) },
+    None => quote! { None },
+  };
+  let thread_name = match &attr.thread_name {
+    Some(name) => quote! { Some(#name) },
+    None => quote! { None },
+  };
+
+  // Split the function out into individual parts
   let vis = func.vis;
   let mut sig = func.sig;
   let name = sig.ident.clone();
   let asyncness = sig.asyncness.take();
   let block = func.block;
+  // Snapshotting the whole environment is only worth it for tests that
+  // compare against it
+  let snapshot_env = if mentions(quote! { #block }, "assert_env_unchanged") {
+    quote! { assay::snapshot_env(); }
+  } else {
+    quote! {}
+  };
+  let binding = attr
+    .fixture_binding
+    .clone()
+    .unwrap_or_else(|| Ident::new("fixture", Span2::call_site()));
+  let (fixture_param, fixture_arg) = match &attr.fixture {
+    Some(ty) => (quote! { #binding: &mut #ty }, quote! { fixture }),
+    None => (quote! {}, quote! {}),
+  };
   let body = if asyncness.is_some() {
     #[cfg(not(feature = "async"))]
     compile_error!("You cannot use the async functionality in `assay` without specifiying a runtime. This error is occurring because you turned off the default features. Possible feature values are:\n- async-tokio-runtime\n- async-std-runtime");
     quote! {
-      async fn inner_async() -> assay::Result<()> {
+      #paused_check
+      async fn inner_async(#fixture_param) -> assay::Result<()> {
+        #bindings
         #block
         Ok(())
       }
-      assay::async_runtime::Runtime::block_on(inner_async())??;
+      assay::async_runtime::Runtime::block_on_with(
+        inner_async(#fixture_arg),
+        assay::async_runtime::Config {
+          paused: #paused,
+          thread_stack_size: #thread_stack_size,
+          thread_name: #thread_name,
+        },
+      )??;
+    }
+  } else if attr.fixture.is_some() {
+    quote! {
+      (|#fixture_param| -> assay::Result<()> {
+        #block
+        Ok(())
+      })(fixture)?;
     }
   } else {
     quote! { #block }
   };
+  // An `async fn` can't see the child's locals so it makes its own bindings
+  let bindings = if asyncness.is_some() {
+    quote! {}
+  } else {
+    bindings
+  };
+
+  // Time each run of the body and report on them once they're all done
+  let body = match &attr.bench {
+    Some(Bench {
+      iterations,
+      max_median,
+    }) => {
+      if let Some(asyncness) = asyncness {
+        return Error::new(asyncness.span, "`bench` does not support async tests")
+          .to_compile_error();
+      }
+      let max_median = match max_median {
+        Some(max) => quote! { Some(#max) },
+        None => quote! { None },
+      };
+      quote! {
+        let mut samples = Vec::with_capacity(#iterations);
+        for _ in 0..#iterations {
+          let start = std::time::Instant::now();
+          #body
+          samples.push(start.elapsed());
+        }
+        assay::bench_report(stringify!(#name), &mut samples, #max_median);
+      }
+    }
+    None => body,
+  };
+
+  // Run the body with a mutable borrow of the fixture and make sure that it's
+  // torn down afterwards no matter how the body exited
+  let body = match &attr.fixture {
+    Some(ty) => quote! {
+      let mut fixture = <#ty as assay::TestFixture>::setup()?;
+      let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| -> assay::Result<()> {
+        let fixture = &mut fixture;
+        #body
+        Ok(())
+      }));
+      let teardown = assay::TestFixture::teardown(fixture);
+      match result {
+        Ok(result) => {
+          result?;
+          teardown?;
+        }
+        Err(panic) => std::panic::resume_unwind(panic),
+      }
+    },
+    None => body,
+  };
+
+  // Skip the test without spawning a child if the probe says it can't run
+  // here. A child spawned by the parent doesn't need to probe again
+  let require = match &attr.require {
+    Some(probe) => quote! {
+      if std::env::var("ASSAY_SPLIT").as_deref() != Ok("1") && !(#probe)() {
+        assay::report_skip(
+          stringify!(#name),
+          concat!("`", stringify!(#probe), "` returned false"),
+        );
+        return Ok(());
+      }
+    },
+    None => quote! {},
+  };
+  let skip_on = if attr.skip_on.is_empty() {
+    quote! {}
+  } else {
+    let oses = &attr.skip_on;
+    quote! {
+      if [#(#oses),*].contains(&std::env::consts::OS) {
+        assay::report_skip(
+          stringify!(#name),
+          &format!("skipped on {}", std::env::consts::OS),
+        );
+        return Ok(());
+      }
+    }
+  };
+
+  // A benchmark's timings are printed by the child so have it show the output
+  // of the test even though it passed and print it again in the parent
+  let (show_output, print_output) = if attr.bench.is_some() {
+    (
+      quote! {
+        if !args.iter().any(|arg| arg == "--show-output") {
+          args.push("--show-output".to_string());
+        }
+      },
+      quote! {
+        let stdout_line = format!("---- {name} stdout ----");
+        let output = stdout
+          .lines()
+          .skip_while(|line| line != &stdout_line)
+          .skip(1)
+          .take_while(|s| !s.starts_with("----") && !s.starts_with("successes:"))
+          .collect::<Vec<&str>>()
+          .join("\n");
+        println!("{}", output.trim());
+      },
+    )
+  } else {
+    (quote! {}, quote! {})
+  };
+
+  // Checking stderr means the child can't capture it so have it print
+  // everything straight away instead
+  let uncaptured = [
+    ("forbid_stderr", attr.forbid_stderr.is_some()),
+    ("inspect_output", attr.inspect_output.is_some()),
+    ("stdout_golden", attr.stdout_golden.is_some()),
+  ];
+  let no_capture = match uncaptured.iter().find(|(_, set)| *set) {
+    Some((name, _)) => {
+      if attr.bench.is_some() {
+        return Error::new(
+          Span::call_site().into(),
+          format!("`{name}` cannot be used with `bench`"),
+        )
+        .to_compile_error();
+      }
+      quote! {
+        if !args.iter().any(|arg| arg == "--nocapture" || arg == "--no-capture") {
+          args.push("--nocapture".to_string());
+        }
+      }
+    }
+    None => quote! {},
+  };
+  // Measuring the child's peak memory means waiting on it ourselves instead of
+  // letting `Command::output` do it
+  let command = quote! {
+    std::process::Command::new(&args[0])
+      .args(if args.len() == 1 { &[] } else { &args[1..] })
+      .env("ASSAY_SPLIT", "1")
+  };
+  let (run_child, check_memory) = match attr.max_memory {
+    Some(max) => (
+      quote! {
+        let (out, peak_memory) =
+          assay::output_with_peak_memory(#command).expect("executed a subprocess");
+      },
+      quote! { assay::check_peak_memory(peak_memory, #max); },
+    ),
+    None => (
+      quote! { let out = #command.output().expect("executed a subprocess"); },
+      quote! {},
+    ),
+  };
+  let check_stderr = match &attr.forbid_stderr {
+    Some(allowed) => quote! {
+      let allowed: &[&str] = &[#(#allowed),*];
+      let stderr = String::from_utf8_lossy(&out.stderr);
+      let unexpected = stderr
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !allowed.iter().any(|a| line.contains(a)))
+        .collect::<Vec<_>>();
+      if !unexpected.is_empty() {
+        panic!(
+          "assertion failed: test wrote to stderr with `forbid_stderr` set\n\n{}",
+          unexpected.join("\n")
+        );
+      }
+    },
+    None => quote! {},
+  };
+
+  let inline = attr.inline.is_some();
+
+  // Swap which macros `assert_eq!` and `assert_ne!` refer to inside the child
+  let eq_asserts = if attr.sorted_asserts {
+    quote! { assert_eq_sorted as assert_eq, assert_ne_sorted as assert_ne }
+  } else {
+    quote! { assert_eq, assert_ne }
+  };
+
+  // Give the hook the failed test's output before the failure is reported
+  let on_failure = match &attr.on_failure {
+    Some(hook) => quote! {
+      if stdout.contains(&format!("{name} ... FAILED"))
+        || stdout.contains(&format!("{name} - should panic ... FAILED"))
+      {
+        let stdout_line = format!("---- {name} stdout ----");
+        let output = stdout
+          .lines()
+          .skip_while(|line| line != &stdout_line)
+          .skip(1)
+          .take_while(|s| !s.starts_with("----") && !s.starts_with("failures:"))
+          .collect::<Vec<&str>>()
+          .join("\n");
+        let hook: fn(&str) = #hook;
+        hook(&output);
+      }
+    },
+    None => quote! {},
+  };
+
+  // Hand the hook everything the child printed once it's passed
+  let inspect_output = match &attr.inspect_output {
+    Some(hook) => quote! {
+      let hook: fn(&str, &str) -> assay::Result<()> = #hook;
+      assay::eyre::WrapErr::wrap_err(
+        hook(&stdout, &String::from_utf8_lossy(&out.stderr)),
+        "inspect_output failed",
+      )?;
+    },
+    None => quote! {},
+  };
+
+  // The child marks where the test's own output starts and ends so it can be
+  // told apart from what libtest prints around it
+  let (stdout_begin, stdout_end, check_stdout_golden) = match &attr.stdout_golden {
+    Some(golden) => (
+      quote! { assay::begin_stdout_golden(); },
+      quote! { assay::end_stdout_golden(); },
+      quote! {
+        if let Err(message) = assay::check_stdout_golden(
+          &stdout,
+          &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(#golden),
+        ) {
+          panic!("assertion failed: {}", message);
+        }
+      },
+    ),
+    None => (quote! {}, quote! {}, quote! {}),
+  };
+
+  // A custom separator like `__` isn't snake case
+  let allow_name = attr
+    .name_separator
+    .as_ref()
+    .map(|_| quote! { #[allow(non_snake_case)] });
 
   let fn_sig = if attr.should_panic {
     quote! { #vis #sig }
@@ -196,34 +1791,142 @@ pub fn assay(attr: TokenStream, item: TokenStream) -> TokenStream {
     quote! { Ok(()) }
   };
 
+  // An error returned with `?` counts as the test panicking. It's panicked on
+  // directly rather than through `unwrap` so the message is the error itself,
+  // starting with what it displays as, like a test returning it would show
   let child = if attr.should_panic {
-    quote! { child().unwrap() }
+    quote! {
+      if let Err(e) = result {
+        panic!("{e:?}");
+      }
+    }
+  } else {
+    quote! { result }
+  };
+  let expects_panic = attr.should_panic;
+  // Decides whether a temp dir kept with `keep_on_failure` stays once the test
+  // is over, which it only does when the test failed. Inline tests have no
+  // temp dir to keep.
+  let (kept_dir, kept_failed) = if attr.inline.is_some() {
+    (quote! {}, quote! {})
   } else {
-    quote! { child() }
+    let kept_dir = if attr.keep_on_failure {
+      quote! { let mut kept = Some(assay::KeptDir::new(#expects_panic)); }
+    } else {
+      quote! { let mut kept = assay::KeptDir::from_env(#expects_panic); }
+    };
+    let kept_failed = quote! {
+      if let Some(kept) = &mut kept {
+        kept.failed = result.is_err();
+      }
+    };
+    (kept_dir, kept_failed)
   };
 
+  // If the test needs a thread configured differently from the one libtest
+  // gives us run the test body on a new one instead
+  let child_name = if attr.thread_stack_size.is_some() || attr.thread_name.is_some() {
+    quote! { child_thread }
+  } else {
+    quote! { child }
+  };
+  let child_thread = if attr.thread_stack_size.is_some() || attr.thread_name.is_some() {
+    let name = attr.thread_name.clone().unwrap_or_else(|| name.to_string());
+    let stack_size = attr
+      .thread_stack_size
+      .map(|size| quote! { .stack_size(#size) });
+    quote! {
+      fn child() -> assay::Result<()> {
+        std::thread::Builder::new()
+          .name(#name.to_string())
+          #stack_size
+          .spawn(child_thread)?
+          .join()
+          .unwrap_or_else(|e| std::panic::resume_unwind(e))
+      }
+    }
+  } else {
+    quote! {}
+  };
+
+  // Removed first so that `env_file` and `env` can set them again
+  let env_remove = attr
+    .env_remove
+    .iter()
+    .map(|key| quote! { std::env::remove_var(#key); });
+  let env_remove = quote! { #(#env_remove)* };
+
+  // Loaded before `env` so that anything set there takes precedence
+  let env_file = match &attr.env_file {
+    Some(path) if attr.inline.is_none() => quote! { fs.load_env_file(#path)?; },
+    _ => quote! {},
+  };
+
+  // Only `inline` tests run in the shared process and so can leak into it.
+  // Recording the baseline before any of them start, as well as before the
+  // first check, means it's always from before anything could have leaked
+  let isolation_baseline = (attr.strict_isolation || attr.inline.is_some())
+    .then(|| quote! { assay::isolation_baseline(); });
+  let strict_isolation = attr
+    .strict_isolation
+    .then(|| quote! { assay::check_isolation(); });
+
   let expanded = quote! {
       #[test]
+      #allow_name
       #should_panic
       #ignore
       #fn_sig {
+        #isolation_baseline
+        #config
+        #skip_on
+        #require
+        #strict_isolation
+        #child_thread
         #[allow(unreachable_code)]
-        fn child() -> assay::Result<()> {
-          use assay::{assert_eq, assert_eq_sorted, assert_ne, net::TestAddress};
+        fn #child_name() -> assay::Result<()> {
+          // Calls the test module's own `__assay_setup` and `__assay_teardown`
+          // if it has them, as the glob of the module inside each function
+          // shadows the glob of the fallbacks around it
+          mod __assay_hooks {
+            #[allow(unused_imports)]
+            use assay::__fallback::*;
+
+            pub fn setup() -> assay::Result<()> {
+              #[allow(unused_imports)]
+              use super::*;
+              assay::ModuleHookResult::into_result(__assay_setup())
+            }
+
+            pub fn teardown() -> assay::Result<()> {
+              #[allow(unused_imports)]
+              use super::*;
+              assay::ModuleHookResult::into_result(__assay_teardown())
+            }
+          }
+          use assay::{#eq_asserts, assert_eq_sorted, net::TestAddress};
+          #rlimit_nofile
           #include
+          #mock_time
+          __assay_hooks::setup()?;
           #setup
+          #env_remove
+          #env_file
           #env
+          #bindings
+          #snapshot_env
+          #stdout_begin
           #body
           #teardown
+          __assay_hooks::teardown()?;
           Ok(())
         }
 
-      if std::env::var("NEXTEST_EXECUTION_MODE")
-        .ok()
-        .as_ref()
-        .map(|s| s.as_str() == "process-per-test")
-        .unwrap_or(false)
-      {
+      if #inline || assay::is_split_child() {
+        #kept_dir
+        let result = child();
+        #stdout_end
+        #kept_failed
         #child
       } else {
         let name = {
@@ -235,41 +1938,55 @@ pub fn assay(attr: TokenStream, item: TokenStream) -> TokenStream {
           module.push(stringify!(#name));
           module.join("::")
         };
-        if std::env::var("ASSAY_SPLIT")
-            .as_ref()
-            .map(|s| s.as_str() != "1")
-            .unwrap_or(true)
-        {
-          let mut args = std::env::args().collect::<Vec<String>>();
-          if !args.contains(&name) {
-            args.push(name.clone());
-          }
-          let out = std::process::Command::new(&args[0])
-            .args(if args.len() == 1 { &[] } else { &args[1..] })
-            .env("ASSAY_SPLIT", "1")
-            .output()
-            .expect("executed a subprocess");
-          let stdout = String::from_utf8(out.stdout).unwrap();
-          if stdout.contains(&format!("{name} - should panic ... ok")) || stdout.contains(&format!("{name} ... FAILED")) {
-            let stdout_line = format!("---- {name} stdout ----");
-            let split = stdout
-              .lines()
-              .skip_while(|line| line != &stdout_line)
-              .skip(1)
-              .take_while(|s| !s.starts_with("----") && !s.starts_with("failures:"))
-              .collect::<Vec<&str>>()
-              .join("\n");
-            assay::panic_replace();
-            panic!("ASSAY_PANIC_INTERNAL_MESSAGE\n{split}")
-          }
-          #ret
-        } else{
-          #child
+        let mut args = assay::child_args(&name);
+        #show_output
+        #no_capture
+        // An uncaptured child's failure output comes from the panic message on
+        // its stderr rather than libtest's captured output
+        let uncaptured = args.iter().any(|arg| arg == "--nocapture" || arg == "--no-capture");
+        let started = std::time::Instant::now();
+        #run_child
+        let stdout = String::from_utf8(out.stdout).unwrap();
+        let _junit = assay::JunitRecorder::from_env(&name, started, #expect_panic, &stdout, &out.stderr);
+        #on_failure
+        #expected_panic
+        if stdout.contains(&format!("{name} - should panic ... ok")) || stdout.contains(&format!("{name} ... FAILED")) {
+          let stdout_line = format!("---- {name} stdout ----");
+          let split = stdout
+            .lines()
+            .skip_while(|line| line != &stdout_line)
+            .skip(1)
+            .take_while(|s| !s.starts_with("----") && !s.starts_with("failures:"))
+            .collect::<Vec<&str>>()
+            .join("\n");
+          let split = if uncaptured {
+            String::from_utf8_lossy(&out.stderr).trim().to_string()
+          } else {
+            split
+          };
+          assay::panic_replace();
+          panic!("ASSAY_PANIC_INTERNAL_MESSAGE\n{split}")
         }
+        // The child never got as far as reporting a result, like when it
+        // aborted instead of unwinding, so all we have is how it exited
+        if !out.status.success() && !stdout.contains("\ntest result: ") {
+          assay::panic_replace();
+          panic!(
+            "ASSAY_PANIC_INTERNAL_MESSAGE\ntest process exited without a result ({})\n\n{}\n{}",
+            out.status,
+            stdout.trim(),
+            String::from_utf8_lossy(&out.stderr).trim()
+          )
+        }
+        #print_output
+        #check_stderr
+        #check_memory
+        #inspect_output
+        #check_stdout_golden
+        #ret
       }
     }
   };
 
-  // Hand the output tokens back to the compiler.
-  TokenStream::from(expanded)
+  expanded
 }
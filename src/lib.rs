@@ -17,48 +17,2435 @@
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/HOW_TO_USE.md"))]
 
 pub mod net;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 pub use assay_proc_macro::assay;
 pub use eyre;
-pub use pretty_assertions_sorted::{assert_eq, assert_eq_sorted, assert_ne};
+#[doc(hidden)]
+pub use pretty_assertions_sorted;
+pub use pretty_assertions_sorted::{assert_eq, assert_ne};
+
+use eyre::{bail, WrapErr};
+use std::{
+  any::Any,
+  cell::{Cell, RefCell},
+  env,
+  fs::{copy, create_dir_all, metadata, set_permissions},
+  panic,
+  path::{Component, Path, PathBuf},
+  sync::{Mutex, OnceLock},
+  time::{Duration, Instant},
+};
+use tempfile::{Builder, TempDir};
+
+pub type Result<T> = std::result::Result<T, eyre::Report>;
+
+/// State that a test needs set up before it runs and cleaned up after, for use
+/// with `#[assay(fixture = MyFixture)]`. The body can use the value through a
+/// `fixture` binding of type `&mut MyFixture`.
+pub trait TestFixture: Sized {
+  /// Create the fixture before the test body runs
+  fn setup() -> Result<Self>;
+  /// Clean up the fixture after the test body has run, even if the body
+  /// returned an error or panicked
+  fn teardown(self) -> Result<()>;
+}
+
+/// Whether the current process is the one actually running the test body.
+///
+/// This is `true` when either:
+/// - `ASSAY_SPLIT` is set to `1`, which `assay` does for the process it spawns
+///   to run each test in under `cargo test`
+/// - `NEXTEST_EXECUTION_MODE` is set to `process-per-test`, as `cargo nextest`
+///   already runs every test in its own process
+///
+/// Otherwise this is the parent process that spawns the child and reports its
+/// results.
+pub fn is_split_child() -> bool {
+  env::var("ASSAY_SPLIT").is_ok_and(|s| s == "1")
+    || env::var("NEXTEST_EXECUTION_MODE").is_ok_and(|s| s == "process-per-test")
+}
+
+/// Say why the test `name` was skipped. It's written straight to stderr rather
+/// than with `eprintln!` so libtest doesn't capture it and hide it along with
+/// the output of passing tests.
+#[doc(hidden)]
+pub fn report_skip(name: &str, reason: &str) {
+  let _ = std::io::Write::write_all(
+    &mut std::io::stderr(),
+    format!("skipping {name}: {reason}\n").as_bytes(),
+  );
+}
+
+/// The arguments to run only the test `name` in the child process, built from
+/// the ones the parent was run with.
+///
+/// Test name filters, `--skip`, and `--exact` aren't passed along. A filter
+/// like `--ignored assert` would otherwise also match sibling tests in the
+/// child, which would then run alongside it in its working directory and
+/// delete each other's files, so the child only gets `name` with `--exact`.
+/// These are passed along as they are:
+/// - `--ignored` and `--include-ignored`, so ignored tests run in the child
+/// - `--nocapture`, `--no-capture`, and `--show-output`, so output is shown
+/// - `--test-threads`, which makes no difference with only one test to run
+///
+/// Everything else is dropped. `--format`, `-q`, `--quiet`, and `--color`
+/// would change the child's output away from the plain text the parent reads
+/// results from, and the rest, like `--list` or `--logfile`, are about the run
+/// as a whole which only the parent is in charge of.
+#[doc(hidden)]
+pub fn child_args(name: &str) -> Vec<String> {
+  let mut parent = env::args();
+  let mut args = parent.next().into_iter().collect::<Vec<_>>();
+  while let Some(arg) = parent.next() {
+    match arg.as_str() {
+      "--ignored" | "--include-ignored" | "--nocapture" | "--no-capture" | "--show-output" => {
+        args.push(arg)
+      }
+      "--test-threads" => {
+        args.push(arg);
+        args.extend(parent.next());
+      }
+      _ if arg.starts_with("--test-threads=") => args.push(arg),
+      // Skip the value along with flags that take one in a separate argument
+      "--skip" | "--format" | "--color" | "--logfile" | "-Z" | "--shuffle-seed" => {
+        parent.next();
+      }
+      _ => {}
+    }
+  }
+  args.push(name.to_string());
+  args.push("--exact".to_string());
+  args
+}
+
+/// What `#[assay]` joins `args` with when passing them to the test
+const ARGS_SEPARATOR: char = '\x1f';
+
+/// The arguments given with `#[assay(args = [...])]`, for code under test that
+/// would otherwise read the test harness's own arguments. This is empty when
+/// no `args` were given.
+pub fn test_args() -> Vec<String> {
+  match env::var("ASSAY_ARGS") {
+    Ok(args) if !args.is_empty() => args.split(ARGS_SEPARATOR).map(String::from).collect(),
+    _ => Vec::new(),
+  }
+}
+
+/// No-op stand-ins for `__assay_setup` and `__assay_teardown` that `#[assay]`
+/// falls back to when the test's module doesn't define its own
+#[doc(hidden)]
+pub mod __fallback {
+  pub fn __assay_setup() {}
+  pub fn __assay_teardown() {}
+}
+
+/// What a module's `__assay_setup` or `__assay_teardown` may return
+#[doc(hidden)]
+pub trait ModuleHookResult {
+  fn into_result(self) -> Result<()>;
+}
+
+impl ModuleHookResult for () {
+  fn into_result(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<E: Into<eyre::Report>> ModuleHookResult for std::result::Result<(), E> {
+  fn into_result(self) -> Result<()> {
+    self.map_err(Into::into)
+  }
+}
+
+/// A finished test as recorded for the JUnit report
+struct JunitCase {
+  name: String,
+  time: Duration,
+  failure: Option<String>,
+}
+
+static JUNIT_CASES: Mutex<Vec<JunitCase>> = Mutex::new(Vec::new());
+
+/// Records how a test went in the JUnit report when it's dropped at the end of
+/// the test. A test that was expected to panic passes if the thread is
+/// panicking by then and fails otherwise.
+#[doc(hidden)]
+pub struct JunitRecorder<'a> {
+  path: String,
+  name: &'a str,
+  started: Instant,
+  should_panic: bool,
+  stdout: &'a str,
+  stderr: &'a [u8],
+}
+
+impl<'a> JunitRecorder<'a> {
+  /// A recorder for the test `name`, or `None` if `ASSAY_JUNIT` isn't set and
+  /// there's no report to write
+  pub fn from_env(
+    name: &'a str,
+    started: Instant,
+    should_panic: bool,
+    stdout: &'a str,
+    stderr: &'a [u8],
+  ) -> Option<Self> {
+    let path = env::var("ASSAY_JUNIT")
+      .ok()
+      .filter(|path| !path.is_empty())?;
+    Some(Self {
+      path,
+      name,
+      started,
+      should_panic,
+      stdout,
+      stderr,
+    })
+  }
+}
+
+impl Drop for JunitRecorder<'_> {
+  fn drop(&mut self) {
+    let failure = (std::thread::panicking() != self.should_panic).then(|| {
+      let header = format!("---- {} stdout ----", self.name);
+      let section = self
+        .stdout
+        .lines()
+        .skip_while(|line| *line != header)
+        .skip(1)
+        .take_while(|line| !line.starts_with("----") && !line.starts_with("failures:"))
+        .collect::<Vec<_>>()
+        .join("\n");
+      match section.trim() {
+        "" => String::from_utf8_lossy(self.stderr).trim().to_string(),
+        section => section.to_string(),
+      }
+    });
+    let mut cases = JUNIT_CASES.lock().unwrap_or_else(|e| e.into_inner());
+    cases.push(JunitCase {
+      name: self.name.to_string(),
+      time: self.started.elapsed(),
+      failure,
+    });
+    // Rewrite the whole report after every test as there's no hook for when
+    // the test binary exits, so it's complete once the last test finishes
+    if let Err(e) = write_junit(&self.path, &cases) {
+      eprintln!(
+        "assay: cannot write the JUnit report to `{}`: {e}",
+        self.path
+      );
+    }
+  }
+}
+
+/// The test binary's name without the hash cargo adds to it
+fn binary_name() -> String {
+  let binary = env::args().next().unwrap_or_default();
+  let stem = Path::new(&binary)
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  match stem.rsplit_once('-') {
+    Some((name, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+      name.to_string()
+    }
+    _ => stem,
+  }
+}
+
+fn write_junit(path: &str, cases: &[JunitCase]) -> std::io::Result<()> {
+  let suite = binary_name();
+  let path = PathBuf::from(path.replace("{binary}", &suite));
+  let mut cases = cases.iter().collect::<Vec<_>>();
+  cases.sort_by(|a, b| a.name.cmp(&b.name));
+  let failures = cases.iter().filter(|case| case.failure.is_some()).count();
+  let total: Duration = cases.iter().map(|case| case.time).sum();
+
+  let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+  xml += &format!(
+    "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" time=\"{:.3}\">\n",
+    xml_escape(&suite),
+    cases.len(),
+    total.as_secs_f64()
+  );
+  for case in cases {
+    let attributes = format!(
+      "name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+      xml_escape(&case.name),
+      xml_escape(&suite),
+      case.time.as_secs_f64()
+    );
+    match &case.failure {
+      None => xml += &format!("    <testcase {attributes}/>\n"),
+      Some(failure) => {
+        let failure = xml_escape(failure);
+        // Use the panic's message rather than the line saying where it was
+        let mut lines = failure.lines().filter(|line| !line.trim().is_empty());
+        let message = match lines.next() {
+          Some(line) if line.starts_with("thread ") && line.contains(" panicked at ") => {
+            lines.next().unwrap_or(line)
+          }
+          line => line.unwrap_or("test failed"),
+        };
+        xml += &format!(
+          "    <testcase {attributes}>\n      <failure message=\"{message}\">{failure}</failure>\n    </testcase>\n"
+        );
+      }
+    }
+  }
+  xml += "  </testsuite>\n</testsuites>\n";
+
+  if let Some(parent) = path.parent() {
+    create_dir_all(parent)?;
+  }
+  // Write somewhere else first so a half written report is never read
+  let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+  std::fs::write(&temp, xml)?;
+  std::fs::rename(temp, path)
+}
+
+/// Escapes text for XML, dropping terminal color codes and any other control
+/// characters that XML doesn't allow
+fn xml_escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  let mut chars = text.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\x1b' => {
+        // Skip an escape sequence like `\x1b[1;31m` up to its final letter
+        if chars.next() == Some('[') {
+          for c in chars.by_ref() {
+            if c.is_ascii_alphabetic() {
+              break;
+            }
+          }
+        }
+      }
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      '\t' | '\n' | '\r' => escaped.push(c),
+      c if c.is_control() => {}
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// Held while `assay` wraps the panic hook so that two hooks being installed
+/// at once on different threads can't drop one of them
+static PANIC_HOOK_INSTALL: Mutex<()> = Mutex::new(());
+
+#[doc(hidden)]
+pub static PANIC_HOOK_REPLACE: OnceLock<()> = OnceLock::new();
+#[doc(hidden)]
+pub fn panic_replace() {
+  const HEADER: &str = "ASSAY_PANIC_INTERNAL_MESSAGE\n";
+  PANIC_HOOK_REPLACE.get_or_init(|| {
+    let _install = PANIC_HOOK_INSTALL.lock().unwrap_or_else(|e| e.into_inner());
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+      let msg = panic_message(panic_info.payload());
+      if let Some(message) = msg.strip_prefix(HEADER) {
+        println!("{}", message.trim());
+      } else {
+        default(panic_info);
+      }
+    }))
+  });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+  payload
+    .downcast_ref::<&str>()
+    .map(|s| s.to_string())
+    .or_else(|| payload.downcast_ref::<String>().map(|s| s.to_owned()))
+    .unwrap_or_default()
+}
+
+thread_local! {
+  /// How many `catch_panic` calls the current thread is inside of
+  static CATCHING_PANIC: Cell<usize> = const { Cell::new(0) };
+  static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static PANIC_HOOK_CATCH: OnceLock<()> = OnceLock::new();
+
+/// Run `f` and catch any panic it causes, returning the panic's message and
+/// location rather than letting the panic hook print it. Panics on other
+/// threads are still reported as usual.
+#[doc(hidden)]
+pub fn catch_panic<R>(f: impl FnOnce() -> R) -> std::result::Result<R, String> {
+  // The hook is installed once for the whole process and only stays quiet for
+  // threads that are inside a call to this, so it's never swapped back and
+  // forth while other threads might be panicking
+  PANIC_HOOK_CATCH.get_or_init(|| {
+    let _install = PANIC_HOOK_INSTALL.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+      if CATCHING_PANIC.with(Cell::get) > 0 {
+        let location = panic_info.location().map(|l| l.to_string());
+        PANIC_LOCATION.with(|cell| *cell.borrow_mut() = location);
+      } else {
+        previous(panic_info);
+      }
+    }));
+  });
+
+  CATCHING_PANIC.with(|depth| depth.set(depth.get() + 1));
+  let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+  CATCHING_PANIC.with(|depth| depth.set(depth.get() - 1));
+  result.map_err(|payload| {
+    let message = panic_message(&*payload);
+    match PANIC_LOCATION.with(|cell| cell.borrow_mut().take()) {
+      Some(location) => format!("panicked at {location}:\n{message}"),
+      None => message,
+    }
+  })
+}
+
+/// Call a closure that is expected not to panic, evaluating to its return
+/// value. If it does panic then the test fails with the closure's panic
+/// message rather than the panic unwinding through the rest of the test.
+#[macro_export]
+macro_rules! assert_no_panic {
+  ($f:expr $(,)?) => {
+    match $crate::catch_panic($f) {
+      ::std::result::Result::Ok(value) => value,
+      ::std::result::Result::Err(message) => {
+        ::core::panic!("assertion failed: closure panicked\n\n{}", message)
+      }
+    }
+  };
+}
+
+/// Run each assertion even if earlier ones fail, then fail once with every
+/// failure message if any of them did. Each assertion runs in its own scope
+/// so `let` bindings aren't visible to the ones after it.
+#[macro_export]
+macro_rules! assert_all {
+  ($($assertion:stmt);+ $(;)?) => {{
+    let mut total = 0;
+    let mut failures = ::std::vec::Vec::<::std::string::String>::new();
+    $(
+      total += 1;
+      if let ::std::result::Result::Err(message) = $crate::catch_panic(|| {
+        $assertion;
+      }) {
+        failures.push(message.trim_end().to_owned());
+      }
+    )+
+    if !failures.is_empty() {
+      ::core::panic!(
+        "assertion failed: {} of {} assertions failed\n\n{}",
+        failures.len(),
+        total,
+        failures.join("\n\n")
+      );
+    }
+  }};
+}
+
+/// Assert that two paths are the same once `.` and `..` have been resolved
+/// and `\\` has been replaced by `/`. This is purely textual, the paths don't
+/// need to exist and symlinks aren't followed.
+#[macro_export]
+macro_rules! assert_path_eq {
+  ($left:expr, $right:expr $(,)?) => {
+    match (
+      $crate::normalize_path(::core::convert::AsRef::<::std::path::Path>::as_ref(&$left)),
+      $crate::normalize_path(::core::convert::AsRef::<::std::path::Path>::as_ref(&$right)),
+    ) {
+      (left, right) => {
+        if left != right {
+          ::core::panic!(
+            "assertion failed: `(left == right)` after normalizing paths\n\n  left: {}\n right: {}",
+            left,
+            right
+          );
+        }
+      }
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn normalize_path(path: &Path) -> String {
+  let unified = path.to_string_lossy().replace('\\', "/");
+  let mut parts: Vec<Component> = Vec::new();
+  for component in Path::new(&unified).components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir => match parts.last() {
+        Some(Component::Normal(_)) => {
+          parts.pop();
+        }
+        // `..` at the root is still the root
+        Some(Component::RootDir | Component::Prefix(_)) => {}
+        _ => parts.push(component),
+      },
+      _ => parts.push(component),
+    }
+  }
+  let normalized = parts.iter().collect::<PathBuf>();
+  match normalized.to_string_lossy().replace('\\', "/") {
+    empty if empty.is_empty() => ".".into(),
+    normalized => normalized,
+  }
+}
+
+/// Assert that `$cmp(&actual, &expected)` returns `true` for comparisons that
+/// `assert_eq!` is too strict for, like approximately equal floats. Both values
+/// are printed on failure.
+#[macro_export]
+macro_rules! assert_with {
+  ($actual:expr, $expected:expr, $cmp:expr $(,)?) => {
+    match (&($actual), &($expected)) {
+      (actual, expected) => {
+        if !($cmp)(actual, expected) {
+          ::core::panic!(
+            "assertion failed: `{}(actual, expected)`\n\n  actual: {:?}\nexpected: {:?}",
+            ::core::stringify!($cmp),
+            actual,
+            expected
+          );
+        }
+      }
+    }
+  };
+}
+
+/// Assert that `$result` is `Ok` with a value equal to `$expected`. If it's an
+/// `Err` the failure shows just the error instead of the whole `Result`.
+#[macro_export]
+macro_rules! assert_ok_eq {
+  ($result:expr, $expected:expr $(,)?) => {
+    match &($result) {
+      ::std::result::Result::Ok(value) => {
+        $crate::assert_eq!(*value, $expected, "`Ok` value didn't match")
+      }
+      ::std::result::Result::Err(error) => ::core::panic!(
+        "assertion failed: expected `Ok`, got `Err`\n\n   error: {:?}\nexpected: Ok({:?})",
+        error,
+        $expected
+      ),
+    }
+  };
+}
+
+/// Assert that `$option` is `Some` with a value equal to `$expected`
+#[macro_export]
+macro_rules! assert_some_eq {
+  ($option:expr, $expected:expr $(,)?) => {
+    match &($option) {
+      ::std::option::Option::Some(value) => {
+        $crate::assert_eq!(*value, $expected, "`Some` value didn't match")
+      }
+      ::std::option::Option::None => ::core::panic!(
+        "assertion failed: expected `Some`, got `None`\n\nexpected: Some({:?})",
+        $expected
+      ),
+    }
+  };
+}
+
+/// Assert at compile time that each of the given types is `Send`
+#[macro_export]
+macro_rules! assert_send {
+  ($($ty:ty),+ $(,)?) => {{
+    fn assert_send<T: ?Sized + ::core::marker::Send>() {}
+    $(assert_send::<$ty>();)+
+  }};
+}
+
+/// Assert at compile time that each of the given types is `Sync`
+#[macro_export]
+macro_rules! assert_sync {
+  ($($ty:ty),+ $(,)?) => {{
+    fn assert_sync<T: ?Sized + ::core::marker::Sync>() {}
+    $(assert_sync::<$ty>();)+
+  }};
+}
+
+/// Assert that every item in `$expected` is also in `$actual`, listing the
+/// ones that aren't on failure
+#[macro_export]
+macro_rules! assert_subset {
+  ($expected:expr, $actual:expr $(,)?) => {{
+    let missing = $crate::missing_items($expected, $actual);
+    if !missing.is_empty() {
+      ::core::panic!(
+        "assertion failed: `expected` is not a subset of `actual`\n\nmissing from actual: {:#?}",
+        missing
+      );
+    }
+  }};
+}
+
+/// Assert that every item in `$actual` is also in `$expected`, listing the
+/// ones that aren't on failure
+#[macro_export]
+macro_rules! assert_superset {
+  ($expected:expr, $actual:expr $(,)?) => {{
+    let missing = $crate::missing_items($actual, $expected);
+    if !missing.is_empty() {
+      ::core::panic!(
+        "assertion failed: `expected` is not a superset of `actual`\n\nmissing from expected: {:#?}",
+        missing
+      );
+    }
+  }};
+}
+
+/// The items in `items` that aren't in `from`, in the order they appear
+#[doc(hidden)]
+pub fn missing_items<T: Eq + std::hash::Hash>(
+  items: impl IntoIterator<Item = T>,
+  from: impl IntoIterator<Item = T>,
+) -> Vec<T> {
+  let from = from.into_iter().collect::<std::collections::HashSet<_>>();
+  items
+    .into_iter()
+    .filter(|item| !from.contains(item))
+    .collect()
+}
+
+/// Assert that `$left` and `$right` have the same items, each the same number
+/// of times, in any order. Unlike `assert_eq_sorted!` the items only need to
+/// implement `PartialEq`. The items only in one side are listed on failure.
+#[macro_export]
+macro_rules! assert_eq_unordered {
+  ($left:expr, $right:expr $(,)?) => {{
+    let (only_left, only_right) = $crate::unmatched_items($left, $right);
+    if !only_left.is_empty() || !only_right.is_empty() {
+      ::core::panic!(
+        "assertion failed: `left` and `right` don't have the same items\n\nonly in left: {:#?}\nonly in right: {:#?}",
+        only_left,
+        only_right
+      );
+    }
+  }};
+}
+
+/// The items in `left` and in `right` that couldn't be paired with an equal
+/// item from the other side
+#[doc(hidden)]
+pub fn unmatched_items<T: PartialEq>(
+  left: impl IntoIterator<Item = T>,
+  right: impl IntoIterator<Item = T>,
+) -> (Vec<T>, Vec<T>) {
+  let mut only_right = right.into_iter().collect::<Vec<_>>();
+  let only_left = left
+    .into_iter()
+    .filter(
+      |item| match only_right.iter().position(|other| other == item) {
+        Some(index) => {
+          only_right.remove(index);
+          false
+        }
+        None => true,
+      },
+    )
+    .collect();
+  (only_left, only_right)
+}
+
+/// Assert that the keys `$key` projects out of each item in `$items` never
+/// decrease, like timestamps on a list of events. The first pair of items that
+/// are out of order is shown on failure.
+#[macro_export]
+macro_rules! assert_ordered {
+  ($items:expr, $key:expr $(,)?) => {
+    if let ::std::option::Option::Some(message) = $crate::first_inversion($items, $key) {
+      ::core::panic!(
+        "assertion failed: items are not ordered by `{}`\n\n{}",
+        ::core::stringify!($key),
+        message
+      );
+    }
+  };
+}
+
+/// Describes the first item in `items` whose key is less than the one before it
+#[doc(hidden)]
+pub fn first_inversion<T: std::fmt::Debug, K: PartialOrd + std::fmt::Debug>(
+  items: impl IntoIterator<Item = T>,
+  key: impl Fn(&T) -> K,
+) -> Option<String> {
+  let mut items = items.into_iter().enumerate();
+  let (_, mut previous) = items.next()?;
+  let mut previous_key = key(&previous);
+  for (index, item) in items {
+    let item_key = key(&item);
+    if previous_key > item_key {
+      return Some(format!(
+        "item {} has key {:?}: {:#?}\nitem {} has key {:?}: {:#?}",
+        index - 1,
+        previous_key,
+        previous,
+        index,
+        item_key,
+        item
+      ));
+    }
+    previous = item;
+    previous_key = item_key;
+  }
+  None
+}
+
+/// Assert that `$sorted` is a stable sort of `$original` by the keys `$key`
+/// projects out of each item, so any items with equal keys are in the same
+/// order they were originally. The first pair of items that swapped places is
+/// shown on failure.
+#[macro_export]
+macro_rules! assert_stable_sort {
+  ($original:expr, $sorted:expr, $key:expr $(,)?) => {
+    if let ::std::option::Option::Some(message) = $crate::first_unstable(
+      ::core::convert::AsRef::<[_]>::as_ref(&$original),
+      ::core::convert::AsRef::<[_]>::as_ref(&$sorted),
+      $key,
+    ) {
+      ::core::panic!(
+        "assertion failed: sort by `{}` is not stable\n\n{}",
+        ::core::stringify!($key),
+        message
+      );
+    }
+  };
+}
+
+/// Describes the first pair of items in `original` with equal keys that are
+/// the other way around in `sorted`. Each sorted item is matched back to the
+/// first unused original item equal to it, so identical items never count as
+/// swapped
+#[doc(hidden)]
+pub fn first_unstable<T: PartialEq + std::fmt::Debug, K: PartialEq + std::fmt::Debug>(
+  original: &[T],
+  sorted: &[T],
+  key: impl Fn(&T) -> K,
+) -> Option<String> {
+  if original.len() != sorted.len() {
+    return Some(format!(
+      "there are {} sorted items but {} original ones",
+      sorted.len(),
+      original.len()
+    ));
+  }
+  let mut used = vec![false; original.len()];
+  // The furthest original index placed so far for each key
+  let mut placed: Vec<(K, usize)> = Vec::new();
+  for (position, item) in sorted.iter().enumerate() {
+    let Some(index) = (0..original.len()).find(|&i| !used[i] && original[i] == *item) else {
+      return Some(format!(
+        "sorted item {position} isn't one of the original items: {item:#?}"
+      ));
+    };
+    used[index] = true;
+    let item_key = key(item);
+    match placed
+      .iter_mut()
+      .find(|(placed_key, _)| *placed_key == item_key)
+    {
+      Some((_, previous)) if *previous > index => {
+        return Some(format!(
+          "original items {index} and {previous} both have key {item_key:?} but were swapped\n\nitem {index}: {:#?}\nitem {previous}: {:#?}",
+          original[index], original[*previous]
+        ));
+      }
+      Some((_, previous)) => *previous = index,
+      None => placed.push((item_key, index)),
+    }
+  }
+  None
+}
+
+/// Assert that each item in `$items` is strictly greater than the one before
+/// it, so unlike `assert_ordered!` two equal items in a row fail. The first
+/// pair that isn't increasing is shown on failure.
+#[macro_export]
+macro_rules! assert_increasing {
+  ($items:expr $(,)?) => {
+    if let ::std::option::Option::Some(message) = $crate::first_non_monotonic($items, true) {
+      ::core::panic!(
+        "assertion failed: items are not strictly increasing\n\n{}",
+        message
+      );
+    }
+  };
+}
+
+/// Assert that each item in `$items` is strictly less than the one before it.
+/// The first pair that isn't decreasing is shown on failure.
+#[macro_export]
+macro_rules! assert_decreasing {
+  ($items:expr $(,)?) => {
+    if let ::std::option::Option::Some(message) = $crate::first_non_monotonic($items, false) {
+      ::core::panic!(
+        "assertion failed: items are not strictly decreasing\n\n{}",
+        message
+      );
+    }
+  };
+}
+
+/// Describes the first pair of items in `items` that aren't strictly
+/// increasing, or decreasing if `increasing` is false. Items that can't be
+/// compared, like `NaN`, count as out of order
+#[doc(hidden)]
+pub fn first_non_monotonic<T: PartialOrd + std::fmt::Debug>(
+  items: impl IntoIterator<Item = T>,
+  increasing: bool,
+) -> Option<String> {
+  let mut items = items.into_iter().enumerate();
+  let (_, mut previous) = items.next()?;
+  for (index, item) in items {
+    let in_order = if increasing {
+      previous < item
+    } else {
+      previous > item
+    };
+    if !in_order {
+      return Some(format!(
+        "item {}: {:?}\nitem {}: {:?}",
+        index - 1,
+        previous,
+        index,
+        item
+      ));
+    }
+    previous = item;
+  }
+  None
+}
+
+/// Collects the results of non-fatal checks so that every failure can be
+/// returned together as one error. Unlike `assert_all!` nothing panics, so the
+/// result can be passed along with `?`.
+///
+/// ```
+/// let status = 500;
+/// let body = "";
+/// let mut checks = assay::Checks::new();
+/// checks
+///   .check(status == 200, "status should be 200")
+///   .check(!body.is_empty(), "body should not be empty");
+/// assert!(checks.finish().is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct Checks {
+  total: usize,
+  failures: Vec<String>,
+}
+
+impl Checks {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record a failure with `message` if `condition` is false
+  #[track_caller]
+  pub fn check(&mut self, condition: bool, message: impl std::fmt::Display) -> &mut Self {
+    self.total += 1;
+    if !condition {
+      let location = std::panic::Location::caller();
+      self.failures.push(format!("{message}\n  at {location}"));
+    }
+    self
+  }
+
+  /// `Ok` if every check passed, otherwise an error listing each failed check
+  pub fn finish(self) -> Result<()> {
+    if self.failures.is_empty() {
+      return Ok(());
+    }
+    bail!(
+      "{} of {} checks failed\n\n{}",
+      self.failures.len(),
+      self.total,
+      self.failures.join("\n\n")
+    )
+  }
+}
+
+/// A wrapper around [`pretty_assertions_sorted::assert_eq_sorted`] that sorts
+/// the `Debug` output of both sides before diffing them. When only one side is
+/// an empty collection the failure says so directly instead of showing a diff
+/// that is entirely additions or removals.
+#[macro_export]
+macro_rules! assert_eq_sorted {
+  ($left:expr, $right:expr $(,)?) => {
+    match (&($left), &($right)) {
+      (left_val, right_val) => {
+        $crate::assert_eq_sorted!(@empty left_val, right_val, "", "");
+        $crate::pretty_assertions_sorted::assert_eq_sorted!(left_val, right_val);
+      }
+    }
+  };
+  ($left:expr, $right:expr, $($arg:tt)+) => {
+    match (&($left), &($right)) {
+      (left_val, right_val) => {
+        $crate::assert_eq_sorted!(@empty left_val, right_val, ": ", $($arg)+);
+        $crate::pretty_assertions_sorted::assert_eq_sorted!(left_val, right_val, $($arg)+);
+      }
+    }
+  };
+  (@empty $left:expr, $right:expr, $maybe_colon:expr, $($arg:tt)*) => {
+    if !(*$left == *$right) {
+      if let Some(message) = $crate::empty_mismatch($left, $right) {
+        ::core::panic!(
+          "assertion failed: `(left == right)`{}{}\n\n{}\n",
+          $maybe_colon,
+          format_args!($($arg)*),
+          message
+        );
+      }
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn empty_mismatch<L, R>(left: &L, right: &R) -> Option<String>
+where
+  L: std::fmt::Debug + ?Sized,
+  R: std::fmt::Debug + ?Sized,
+{
+  fn is_empty(debug: &str) -> bool {
+    matches!(debug.trim(), "[]" | "{}")
+  }
+  // Say what the empty side is since `[]` on its own doesn't
+  let left_type = short_type_name(std::any::type_name::<L>());
+  let right_type = short_type_name(std::any::type_name::<R>());
+  let left = format!("{left:#?}");
+  let right = format!("{right:#?}");
+  match (is_empty(&left), is_empty(&right)) {
+    (true, false) => Some(format!(
+      "left: <empty {left_type}>\nright ({right_type}): {right}"
+    )),
+    (false, true) => Some(format!(
+      "left ({left_type}): {left}\nright: <empty {right_type}>"
+    )),
+    _ => None,
+  }
+}
+
+/// `name` without the module paths in front of each type, so
+/// `alloc::vec::Vec<i32>` becomes `Vec<i32>`
+fn short_type_name(name: &str) -> String {
+  let mut short = String::new();
+  let mut segment = String::new();
+  for c in name.chars() {
+    if c.is_alphanumeric() || c == '_' || c == ':' {
+      segment.push(c);
+    } else {
+      short.push_str(segment.rsplit("::").next().unwrap_or_default());
+      segment.clear();
+      short.push(c);
+    }
+  }
+  short.push_str(segment.rsplit("::").next().unwrap_or_default());
+  short
+}
+
+/// The `!=` counterpart to [`assert_eq_sorted!`]. On failure the shared value
+/// is printed with its `Debug` output sorted.
+#[macro_export]
+macro_rules! assert_ne_sorted {
+  ($left:expr, $right:expr $(,)?) => {
+    $crate::assert_ne_sorted!($left, $right, "")
+  };
+  ($left:expr, $right:expr, $($arg:tt)+) => {
+    match (&($left), &($right)) {
+      (left_val, right_val) => {
+        if *left_val == *right_val {
+          let message = ::std::format!($($arg)+);
+          ::core::panic!(
+            "assertion failed: `(left != right)`{}{}\n\nBoth sides:\n{:#?}\n",
+            if message.is_empty() { "" } else { ": " },
+            message,
+            $crate::pretty_assertions_sorted::SortedDebug::new(left_val)
+          );
+        }
+      }
+    }
+  };
+}
+
+/// Assert that two values have the same `Debug` output once the entries of any
+/// maps in it have been sorted. Neither side needs to implement `PartialEq`, or
+/// even be the same type, which makes this handy for structs holding a
+/// `HashMap`.
+#[macro_export]
+macro_rules! assert_debug_eq {
+  ($left:expr, $right:expr $(,)?) => {
+    if let ::std::option::Option::Some(diff) = $crate::debug_mismatch(&$left, &$right) {
+      ::core::panic!(
+        "assertion failed: `(left == right)` comparing `Debug` output\n\n{}",
+        diff
+      );
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn debug_mismatch(left: &dyn std::fmt::Debug, right: &dyn std::fmt::Debug) -> Option<String> {
+  use pretty_assertions_sorted::SortedDebug;
+
+  let left = format!("{:#?}", SortedDebug::new(left));
+  let right = format!("{:#?}", SortedDebug::new(right));
+  (left != right)
+    .then(|| pretty_assertions_sorted::Comparison::new(&Lines(left), &Lines(right)).to_string())
+}
+
+/// Assert that two strings are equal once each line has been trimmed, runs of
+/// whitespace within a line collapsed to a single space, and blank lines
+/// dropped. On failure the normalized forms are diffed line by line.
+#[macro_export]
+macro_rules! assert_eq_ignore_whitespace {
+  ($actual:expr, $expected:expr $(,)?) => {
+    if let ::std::option::Option::Some(diff) = $crate::whitespace_mismatch(
+      ::core::convert::AsRef::<str>::as_ref(&$actual),
+      ::core::convert::AsRef::<str>::as_ref(&$expected),
+    ) {
+      ::core::panic!(
+        "assertion failed: `(actual == expected)` ignoring whitespace\n\n{}",
+        diff
+      );
+    }
+  };
+}
+
+/// Shows the string as is so a diff of it is split on its lines
+struct Lines(String);
+
+impl std::fmt::Debug for Lines {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+#[doc(hidden)]
+pub fn whitespace_mismatch(actual: &str, expected: &str) -> Option<String> {
+  fn normalize(text: &str) -> String {
+    text
+      .lines()
+      .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+      .filter(|line| !line.is_empty())
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  let actual = normalize(actual);
+  let expected = normalize(expected);
+  (actual != expected).then(|| {
+    pretty_assertions_sorted::Comparison::new(&Lines(actual), &Lines(expected)).to_string()
+  })
+}
+
+/// Call `$f` `$runs` times and assert that it returned the same value every
+/// time, evaluating to that value. On failure the first run is shown along with
+/// each run that returned something else.
+#[macro_export]
+macro_rules! assert_deterministic {
+  ($runs:expr, $f:expr $(,)?) => {
+    match $crate::compare_runs($runs, $f) {
+      ::std::result::Result::Ok(value) => value,
+      ::std::result::Result::Err(message) => ::core::panic!("assertion failed: {}", message),
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn compare_runs<T: PartialEq + std::fmt::Debug>(
+  runs: usize,
+  mut f: impl FnMut() -> T,
+) -> std::result::Result<T, String> {
+  assert!(
+    runs >= 2,
+    "`assert_deterministic!` needs at least 2 runs to compare"
+  );
+  let first = f();
+  let differing = (2..=runs)
+    .filter_map(|run| {
+      let value = f();
+      (value != first).then(|| format!("run {run}: {value:#?}"))
+    })
+    .collect::<Vec<_>>();
+  if differing.is_empty() {
+    Ok(first)
+  } else {
+    Err(format!(
+      "{} of {runs} runs returned a different value than the first\n\nrun 1: {first:#?}\n{}",
+      differing.len(),
+      differing.join("\n")
+    ))
+  }
+}
+
+/// Poll `$future` once and assert that it's already `Ready`, evaluating to its
+/// output. It's polled with a waker that does nothing so this works in any
+/// test, `async` or not, whatever the runtime.
+#[macro_export]
+macro_rules! assert_ready {
+  ($future:expr $(,)?) => {
+    match $crate::poll_once($future) {
+      ::std::option::Option::Some(output) => output,
+      ::std::option::Option::None => ::core::panic!(
+        "assertion failed: `{}` was still pending when polled",
+        ::core::stringify!($future)
+      ),
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn poll_once<F: std::future::Future>(future: F) -> Option<F::Output> {
+  let mut future = std::pin::pin!(future);
+  let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+  match future.as_mut().poll(&mut context) {
+    std::task::Poll::Ready(output) => Some(output),
+    std::task::Poll::Pending => None,
+  }
+}
+
+/// Call `$step` up to `$iterations` times and assert that two successive
+/// results end up closer together than `$tolerance`, evaluating to the last
+/// result. On failure the last few results are shown.
+#[macro_export]
+macro_rules! assert_converges {
+  ($iterations:expr, $tolerance:expr, $step:expr $(,)?) => {
+    match $crate::converge($iterations, $tolerance, $step) {
+      ::std::result::Result::Ok(value) => value,
+      ::std::result::Result::Err(message) => ::core::panic!("assertion failed: {}", message),
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn converge<T>(
+  iterations: usize,
+  tolerance: T,
+  mut step: impl FnMut() -> T,
+) -> std::result::Result<T, String>
+where
+  T: Copy + PartialOrd + std::ops::Sub<Output = T> + std::fmt::Debug,
+{
+  const SHOWN: usize = 5;
+  assert!(
+    iterations >= 2,
+    "`assert_converges!` needs at least 2 iterations to compare"
+  );
+  let mut last = std::collections::VecDeque::with_capacity(SHOWN);
+  let mut previous = step();
+  last.push_back((1, previous));
+  for iteration in 2..=iterations {
+    let value = step();
+    // Taking the larger from the smaller keeps unsigned types from underflowing
+    let difference = if value > previous {
+      value - previous
+    } else {
+      previous - value
+    };
+    // Written so that a `NaN` difference never counts as converged
+    if difference < tolerance {
+      return Ok(value);
+    }
+    if last.len() == SHOWN {
+      last.pop_front();
+    }
+    last.push_back((iteration, value));
+    previous = value;
+  }
+  let shown = last
+    .iter()
+    .map(|(iteration, value)| format!("iteration {iteration}: {value:?}"))
+    .collect::<Vec<_>>()
+    .join("\n");
+  Err(format!(
+    "did not converge within {iterations} iterations\n\ntolerance: {tolerance:?}\n{shown}"
+  ))
+}
+
+/// Assert that running `$block` takes `$target` give or take `$tolerance`,
+/// evaluating to the value the block produced. Unlike a plain upper bound this
+/// fails if the block finishes too quickly as well as too slowly.
+#[macro_export]
+macro_rules! assert_duration_within {
+  ($target:expr, $tolerance:expr, $block:block $(,)?) => {{
+    let target: ::std::time::Duration = $target;
+    let tolerance: ::std::time::Duration = $tolerance;
+    let start = ::std::time::Instant::now();
+    let value = $block;
+    let elapsed = start.elapsed();
+    if elapsed < target.saturating_sub(tolerance) || elapsed > target.saturating_add(tolerance) {
+      ::core::panic!(
+        "assertion failed: `elapsed` is not within `target ± tolerance`\n\
+         \n  elapsed: {:?}\n   target: {:?}\ntolerance: {:?}",
+        elapsed,
+        target,
+        tolerance
+      );
+    }
+    value
+  }};
+}
+
+/// Assert that calling `$op` `$count` times manages at least `$min_per_sec`
+/// calls a second, evaluating to the measured rate. The rate is worked out
+/// from the total time of all the calls so a single slow call doesn't fail it.
+#[macro_export]
+macro_rules! assert_throughput {
+  ($min_per_sec:expr, $count:expr, $op:expr $(,)?) => {{
+    let min_per_sec = $min_per_sec as f64;
+    let count: usize = $count;
+    let mut op = $op;
+    let start = ::std::time::Instant::now();
+    for _ in 0..count {
+      let _ = op();
+    }
+    let elapsed = start.elapsed();
+    let rate = count as f64 / elapsed.as_secs_f64();
+    if rate < min_per_sec {
+      ::core::panic!(
+        "assertion failed: throughput is below the minimum\n\
+         \n measured: {:.1} ops/sec\n  minimum: {} ops/sec\n    calls: {} in {:?}",
+        rate,
+        min_per_sec,
+        count,
+        elapsed
+      );
+    }
+    rate
+  }};
+}
+
+/// Assert that a spawned child process exits cleanly within a duration such as
+/// `"2s"` or `"500ms"`, evaluating to its exit status. If it's still running
+/// once the time is up it's killed and the assertion fails.
+#[macro_export]
+macro_rules! assert_terminated {
+  ($child:expr, within = $within:expr $(,)?) => {
+    match $crate::wait_terminated(&mut $child, $within) {
+      ::std::result::Result::Ok(status) => status,
+      ::std::result::Result::Err(message) => ::core::panic!("assertion failed: {}", message),
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn wait_terminated(
+  child: &mut std::process::Child,
+  within: &str,
+) -> std::result::Result<std::process::ExitStatus, String> {
+  let timeout = parse_duration(within)
+    .unwrap_or_else(|| panic!("invalid duration `{within}`, expected something like \"2s\""));
+  let pid = child.id();
+  let start = Instant::now();
+  let status = loop {
+    match child.try_wait() {
+      Ok(Some(status)) => break status,
+      Ok(None) if start.elapsed() < timeout => std::thread::sleep(Duration::from_millis(10)),
+      Ok(None) => {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(format!(
+          "child process {pid} did not exit within {within} and was killed"
+        ));
+      }
+      Err(e) => return Err(format!("cannot wait on child process {pid}: {e}")),
+    }
+  };
+  if status.success() {
+    Ok(status)
+  } else {
+    Err(format!(
+      "child process {pid} did not exit cleanly\n\n  status: {status}"
+    ))
+  }
+}
+
+/// Assert that every `acquire` on the `BalanceTracker` `$tracker` has been
+/// matched by a `release`, as a way to catch leaked locks, handles, or
+/// references
+#[macro_export]
+macro_rules! assert_balanced {
+  ($tracker:expr $(,)?) => {
+    if let ::std::result::Result::Err(message) = $crate::BalanceTracker::check_balanced(&$tracker) {
+      ::core::panic!(
+        "assertion failed: `{}` is not balanced\n\n{}",
+        ::core::stringify!($tracker),
+        message
+      );
+    }
+  };
+}
+
+/// Counts acquires and releases of some resource so `assert_balanced!` can
+/// check that they match. It only needs a shared reference to count, so it can
+/// be put in an `Arc` and handed to guards or other threads
+#[derive(Debug, Default)]
+pub struct BalanceTracker {
+  acquired: std::sync::atomic::AtomicUsize,
+  released: std::sync::atomic::AtomicUsize,
+}
+
+impl BalanceTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record that the resource was acquired
+  pub fn acquire(&self) {
+    self
+      .acquired
+      .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+  }
+
+  /// Record that the resource was released
+  pub fn release(&self) {
+    self
+      .released
+      .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+  }
+
+  /// How many acquires haven't been released yet, which is negative if there
+  /// were more releases than acquires
+  pub fn outstanding(&self) -> i64 {
+    let acquired = self.acquired.load(std::sync::atomic::Ordering::SeqCst) as i64;
+    acquired - self.released.load(std::sync::atomic::Ordering::SeqCst) as i64
+  }
+
+  #[doc(hidden)]
+  pub fn check_balanced(&self) -> std::result::Result<(), String> {
+    use std::sync::atomic::Ordering;
+
+    let acquired = self.acquired.load(Ordering::SeqCst);
+    let released = self.released.load(Ordering::SeqCst);
+    let imbalance = match acquired.cmp(&released) {
+      std::cmp::Ordering::Equal => return Ok(()),
+      std::cmp::Ordering::Greater => format!("{} acquired but never released", acquired - released),
+      std::cmp::Ordering::Less => {
+        format!("{} released without being acquired", released - acquired)
+      }
+    };
+    Err(format!(
+      "acquired: {acquired}\nreleased: {released}\n\n{imbalance}"
+    ))
+  }
+}
+
+/// Assert that every sender for the channel receiver `$rx` has been dropped and
+/// there's nothing left for it to receive. Works with `std::sync::mpsc`
+/// receivers and, with the `async-tokio-runtime` feature, `tokio::sync::mpsc`
+/// ones. This never blocks, so a channel that is still open fails right away
+/// rather than waiting to see if it closes.
+#[macro_export]
+macro_rules! assert_closed {
+  ($rx:expr $(,)?) => {
+    if let ::std::result::Result::Err(message) = $crate::ChannelReceiver::check_closed(&mut $rx) {
+      ::core::panic!("assertion failed: {}", message);
+    }
+  };
+}
+
+/// The receiving end of a channel that `assert_closed!` can check
+#[doc(hidden)]
+pub trait ChannelReceiver {
+  fn check_closed(&mut self) -> std::result::Result<(), String>;
+}
+
+const CHANNEL_NOT_EMPTY: &str = "channel still had a value waiting to be received";
+const CHANNEL_OPEN: &str = "channel is still open";
+
+impl<T> ChannelReceiver for std::sync::mpsc::Receiver<T> {
+  fn check_closed(&mut self) -> std::result::Result<(), String> {
+    use std::sync::mpsc::TryRecvError;
+    match self.try_recv() {
+      Ok(_) => Err(CHANNEL_NOT_EMPTY.into()),
+      Err(TryRecvError::Empty) => Err(CHANNEL_OPEN.into()),
+      Err(TryRecvError::Disconnected) => Ok(()),
+    }
+  }
+}
+
+#[cfg(feature = "async-tokio-runtime")]
+impl<T> ChannelReceiver for tokio::sync::mpsc::Receiver<T> {
+  fn check_closed(&mut self) -> std::result::Result<(), String> {
+    use tokio::sync::mpsc::error::TryRecvError;
+    match self.try_recv() {
+      Ok(_) => Err(CHANNEL_NOT_EMPTY.into()),
+      Err(TryRecvError::Empty) => Err(CHANNEL_OPEN.into()),
+      Err(TryRecvError::Disconnected) => Ok(()),
+    }
+  }
+}
+
+#[cfg(feature = "async-tokio-runtime")]
+impl<T> ChannelReceiver for tokio::sync::mpsc::UnboundedReceiver<T> {
+  fn check_closed(&mut self) -> std::result::Result<(), String> {
+    use tokio::sync::mpsc::error::TryRecvError;
+    match self.try_recv() {
+      Ok(_) => Err(CHANNEL_NOT_EMPTY.into()),
+      Err(TryRecvError::Empty) => Err(CHANNEL_OPEN.into()),
+      Err(TryRecvError::Disconnected) => Ok(()),
+    }
+  }
+}
+
+/// Print the min, median, and max of a benchmarked test's timings, failing if
+/// the median is slower than `max_median`
+#[doc(hidden)]
+pub fn bench_report(name: &str, samples: &mut [Duration], max_median: Option<&str>) {
+  if samples.is_empty() {
+    return;
+  }
+  samples.sort_unstable();
+  let mid = samples.len() / 2;
+  let median = if samples.len().is_multiple_of(2) {
+    (samples[mid - 1] + samples[mid]) / 2
+  } else {
+    samples[mid]
+  };
+  let report = format!(
+    "bench {name}: {} iterations\n     min: {:?}\n  median: {:?}\n     max: {:?}",
+    samples.len(),
+    samples[0],
+    median,
+    samples[samples.len() - 1]
+  );
+  println!("{report}");
+  if let Some(max_median) = max_median {
+    let max = parse_duration(max_median).unwrap_or_else(|| {
+      panic!("invalid duration `{max_median}`, expected something like \"5ms\"")
+    });
+    if median > max {
+      panic!("assertion failed: median of {median:?} is slower than `max_median` of {max_median}");
+    }
+  }
+}
+
+/// Parse a duration like `1.5s`, `500ms`, or `2m`
+fn parse_duration(duration: &str) -> Option<Duration> {
+  let duration = duration.trim();
+  let split = duration
+    .find(|c: char| !c.is_ascii_digit() && c != '.')
+    .unwrap_or(duration.len());
+  let (number, unit) = duration.split_at(split);
+  let number = number.parse::<f64>().ok()?;
+  let seconds = match unit.trim() {
+    "ns" => number / 1e9,
+    "us" | "µs" => number / 1e6,
+    "ms" => number / 1e3,
+    "s" => number,
+    "m" => number * 60.0,
+    "h" => number * 60.0 * 60.0,
+    _ => return None,
+  };
+  Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Assert that some bytes are valid UTF-8, evaluating to them as a `&str`. On
+/// failure the byte offset of the first invalid sequence is reported along
+/// with the text around it.
+#[macro_export]
+macro_rules! assert_valid_utf8 {
+  ($bytes:expr $(,)?) => {{
+    let bytes: &[u8] = ::core::convert::AsRef::as_ref(&$bytes);
+    match ::std::str::from_utf8(bytes) {
+      ::std::result::Result::Ok(text) => text,
+      ::std::result::Result::Err(error) => ::core::panic!(
+        "assertion failed: bytes are not valid UTF-8\n\n{}",
+        $crate::utf8_error_context(bytes, &error)
+      ),
+    }
+  }};
+}
+
+#[doc(hidden)]
+pub fn utf8_error_context(bytes: &[u8], error: &std::str::Utf8Error) -> String {
+  let start = error.valid_up_to();
+  let end = start + error.error_len().unwrap_or(bytes.len() - start);
+  let invalid = bytes[start..end]
+    .iter()
+    .map(|b| format!("{b:02x}"))
+    .collect::<Vec<_>>()
+    .join(" ");
+  let context = &bytes[start.saturating_sub(16)..(end + 16).min(bytes.len())];
+  format!(
+    "invalid sequence at byte {start}: [{invalid}]\n context: {:?}",
+    String::from_utf8_lossy(context)
+  )
+}
+
+/// Decode bytes as UTF-8, replacing invalid sequences with `U+FFFD` like
+/// [`String::from_utf8_lossy`] but printing a warning to stderr if there were
+/// any
+pub fn from_utf8_lossy_logged(bytes: &[u8]) -> std::borrow::Cow<'_, str> {
+  if let Err(error) = std::str::from_utf8(bytes) {
+    eprintln!(
+      "warning: replaced invalid UTF-8 while decoding\n{}",
+      utf8_error_context(bytes, &error)
+    );
+  }
+  String::from_utf8_lossy(bytes)
+}
+
+thread_local! {
+  static ENV_SNAPSHOT: RefCell<Option<Vec<(std::ffi::OsString, std::ffi::OsString)>>> =
+    const { RefCell::new(None) };
+}
+
+/// Assert that no environment variables have been added, removed, or changed
+/// since the test's body started. Every difference is listed on failure.
+///
+/// # Panics
+///
+/// If it isn't used on the thread an `#[assay]` test's body runs on
+#[macro_export]
+macro_rules! assert_env_unchanged {
+  () => {
+    if let ::std::option::Option::Some(message) = $crate::env_changes() {
+      ::core::panic!("assertion failed: {}", message);
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn snapshot_env() {
+  let mut vars = env::vars_os().collect::<Vec<_>>();
+  vars.sort();
+  ENV_SNAPSHOT.with(|snapshot| *snapshot.borrow_mut() = Some(vars));
+}
+
+#[doc(hidden)]
+pub fn env_changes() -> Option<String> {
+  let before = ENV_SNAPSHOT
+    .with(|snapshot| snapshot.borrow().clone())
+    .unwrap_or_else(|| {
+      panic!(
+        "`assert_env_unchanged!` can only be used in the body of an `#[assay]` test, on the thread it runs on"
+      )
+    });
+  let changes = changed_vars(before);
+  (!changes.is_empty()).then(|| {
+    format!(
+      "environment changed since the test started\n\n{}",
+      changes.join("\n")
+    )
+  })
+}
+
+/// A line for every variable added, removed, or changed since `before`,
+/// sorted by name
+fn changed_vars(before: Vec<(std::ffi::OsString, std::ffi::OsString)>) -> Vec<String> {
+  use std::collections::BTreeMap;
+
+  let before = before.into_iter().collect::<BTreeMap<_, _>>();
+  let mut after = env::vars_os().collect::<BTreeMap<_, _>>();
+
+  let mut changes = Vec::new();
+  for (key, old) in &before {
+    match after.remove(key) {
+      None => changes.push(format!("- {}={old:?}", key.to_string_lossy())),
+      Some(new) if &new != old => {
+        changes.push(format!("~ {}: {old:?} -> {new:?}", key.to_string_lossy()))
+      }
+      Some(_) => {}
+    }
+  }
+  for (key, new) in after {
+    changes.push(format!("+ {}={new:?}", key.to_string_lossy()));
+  }
+  changes.sort_by(|a, b| a[2..].cmp(&b[2..]));
+  changes
+}
+
+/// The environment and working directory from when the first `inline` or
+/// `strict_isolation` test in the process started
+#[allow(clippy::type_complexity)]
+static ISOLATION_BASELINE: OnceLock<(
+  Vec<(std::ffi::OsString, std::ffi::OsString)>,
+  Option<PathBuf>,
+)> = OnceLock::new();
+
+#[doc(hidden)]
+pub fn isolation_baseline() {
+  ISOLATION_BASELINE.get_or_init(|| (env::vars_os().collect(), env::current_dir().ok()));
+}
+
+/// Fail if the environment or working directory aren't what they were when
+/// the first `inline` or `strict_isolation` test in the process started, for
+/// `#[assay(strict_isolation)]`
+#[doc(hidden)]
+#[track_caller]
+pub fn check_isolation() {
+  let (vars, dir) = ISOLATION_BASELINE
+    .get()
+    .cloned()
+    .expect("the baseline is recorded before any test checks it");
+  let mut changes = changed_vars(vars);
+  let current = env::current_dir().ok();
+  if current != dir {
+    changes.push(format!("working directory: {dir:?} -> {current:?}"));
+  }
+  if !changes.is_empty() {
+    panic!(
+      "assertion failed: state was left behind by an earlier test in this process\n\n{}",
+      changes.join("\n")
+    );
+  }
+}
+
+/// The instant `#[assay(mock_time)]` froze the clock at and how far it's been
+/// moved since. It's shared by the whole process, rather than being per
+/// thread, so threads spawned by the code under test see the same time. Tests
+/// using it always run in their own process so it can't leak into other tests.
+static MOCK_CLOCK: Mutex<Option<(Instant, Duration)>> = Mutex::new(None);
+
+fn mock_clock() -> std::sync::MutexGuard<'static, Option<(Instant, Duration)>> {
+  MOCK_CLOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Get a handle to the clock for the current test. Code that should be
+/// testable under `#[assay(mock_time)]` can read the time from here instead of
+/// calling `Instant::now` directly.
+pub fn clock() -> Clock {
+  Clock { _private: () }
+}
+
+/// A source of time that can be frozen and moved forward by tests. Without
+/// `#[assay(mock_time)]` this is just the system's monotonic clock.
+///
+/// This is separate from the paused clock `async` tests get with `tokio`.
+/// `advance` and `set` don't move `tokio::time`, and `tokio::time::advance`
+/// or tokio skipping ahead while idle doesn't move this clock.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+  _private: (),
+}
+
+impl Clock {
+  /// The current time according to this clock
+  pub fn now(&self) -> Instant {
+    match *mock_clock() {
+      Some((start, offset)) => start + offset,
+      None => Instant::now(),
+    }
+  }
+
+  /// Move the mocked clock forward by `duration`
+  ///
+  /// # Panics
+  ///
+  /// If the clock isn't mocked with `#[assay(mock_time)]`
+  pub fn advance(&self, duration: Duration) {
+    match &mut *mock_clock() {
+      Some((_, offset)) => *offset += duration,
+      None => panic!("`assay::clock().advance()` requires the test to use `#[assay(mock_time)]`"),
+    }
+  }
+
+  /// Set the mocked clock to `elapsed` after the time it was frozen at. This
+  /// can move the clock backwards for testing code that has to cope with that
+  ///
+  /// # Panics
+  ///
+  /// If the clock isn't mocked with `#[assay(mock_time)]`
+  pub fn set(&self, elapsed: Duration) {
+    match &mut *mock_clock() {
+      Some((_, offset)) => *offset = elapsed,
+      None => panic!("`assay::clock().set()` requires the test to use `#[assay(mock_time)]`"),
+    }
+  }
+
+  /// Whether this clock is frozen and only moves when advanced
+  pub fn is_mocked(&self) -> bool {
+    mock_clock().is_some()
+  }
+}
+
+/// The current time according to [`clock()`], so the same as `Instant::now`
+/// unless the test uses `#[assay(mock_time)]`
+pub fn now() -> Instant {
+  clock().now()
+}
+
+/// Anything that can tell the time. Code that takes one of these rather than
+/// reading a clock itself can be handed a [`MockTime`] in tests and a
+/// [`Clock`] everywhere else.
+pub trait TimeSource {
+  /// The current time according to this source
+  fn now(&self) -> Instant;
+}
+
+impl TimeSource for Clock {
+  fn now(&self) -> Instant {
+    Clock::now(self)
+  }
+}
+
+/// A clock that's frozen when made and only moves when told to. Unlike
+/// `#[assay(mock_time)]` it doesn't need the test to be set up for it and
+/// clones share the same time, so one can be given to the code under test
+/// while the test keeps another to move it forward.
+#[derive(Clone, Debug)]
+pub struct MockTime {
+  start: Instant,
+  elapsed: std::sync::Arc<Mutex<Duration>>,
+}
+
+impl MockTime {
+  /// Make a new clock frozen at the current time
+  pub fn new() -> Self {
+    Self {
+      start: Instant::now(),
+      elapsed: Default::default(),
+    }
+  }
+
+  /// Move the clock forward by `duration`
+  pub fn advance(&self, duration: Duration) {
+    *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+  }
+
+  /// Set the clock to `elapsed` after the time it was made at, which can move
+  /// it backwards
+  pub fn set(&self, elapsed: Duration) {
+    *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) = elapsed;
+  }
+}
+
+impl Default for MockTime {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl TimeSource for MockTime {
+  fn now(&self) -> Instant {
+    self.start + *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+#[doc(hidden)]
+#[cfg(unix)]
+pub fn set_rlimit_nofile(limit: u64) -> Result<()> {
+  let mut rlimit = libc::rlimit {
+    rlim_cur: 0,
+    rlim_max: 0,
+  };
+  // Only the soft limit is changed so it can't go past the hard one
+  if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlimit) } != 0 {
+    return Err(std::io::Error::last_os_error()).wrap_err("cannot read `RLIMIT_NOFILE`");
+  }
+  if limit as libc::rlim_t > rlimit.rlim_max {
+    bail!(
+      "`rlimit_nofile = {limit}` is above the hard limit of {}",
+      rlimit.rlim_max
+    );
+  }
+  rlimit.rlim_cur = limit as libc::rlim_t;
+  if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &rlimit) } != 0 {
+    return Err(std::io::Error::last_os_error()).wrap_err("cannot set `RLIMIT_NOFILE`");
+  }
+  Ok(())
+}
+
+#[doc(hidden)]
+#[cfg(not(unix))]
+pub fn set_rlimit_nofile(_: u64) -> Result<()> {
+  bail!("`rlimit_nofile` is only supported on unix")
+}
+
+/// Run `command` like `Command::output` does, also returning the most memory
+/// it had resident at once in bytes
+#[doc(hidden)]
+#[cfg(unix)]
+pub fn output_with_peak_memory(
+  command: &mut std::process::Command,
+) -> std::io::Result<(std::process::Output, usize)> {
+  use std::{io::Read, os::unix::process::ExitStatusExt, process::Stdio};
+
+  let mut child = command
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+  // Both pipes are read at once so that a child filling one doesn't block
+  let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+  let stderr = std::thread::spawn(move || {
+    let mut stderr = Vec::new();
+    stderr_pipe.read_to_end(&mut stderr).map(|_| stderr)
+  });
+  let mut stdout = Vec::new();
+  child
+    .stdout
+    .take()
+    .expect("stdout is piped")
+    .read_to_end(&mut stdout)?;
+  let stderr = stderr.join().expect("stderr reader panicked")?;
+
+  let pid = child.id() as libc::pid_t;
+  let mut status = 0;
+  let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+  while unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } != pid {
+    let e = std::io::Error::last_os_error();
+    if e.kind() != std::io::ErrorKind::Interrupted {
+      return Err(e);
+    }
+  }
+  // macOS reports the size in bytes and everything else in kilobytes
+  let peak = if cfg!(any(target_os = "macos", target_os = "ios")) {
+    usage.ru_maxrss as usize
+  } else {
+    usage.ru_maxrss as usize * 1024
+  };
+  let output = std::process::Output {
+    status: std::process::ExitStatus::from_raw(status),
+    stdout,
+    stderr,
+  };
+  Ok((output, peak))
+}
+
+#[doc(hidden)]
+#[cfg(not(unix))]
+pub fn output_with_peak_memory(
+  _: &mut std::process::Command,
+) -> std::io::Result<(std::process::Output, usize)> {
+  Err(std::io::Error::new(
+    std::io::ErrorKind::Unsupported,
+    "`max_memory` is only supported on unix",
+  ))
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn check_peak_memory(peak: usize, max: usize) {
+  if peak > max {
+    let mib = |bytes: usize| bytes as f64 / (1 << 20) as f64;
+    panic!(
+      "assertion failed: test used more memory than `max_memory` allows\n\n    peak: {:.1} MiB\n maximum: {:.1} MiB",
+      mib(peak),
+      mib(max)
+    );
+  }
+}
+
+#[doc(hidden)]
+pub fn install_mock_clock() {
+  *mock_clock() = Some((Instant::now(), Duration::ZERO));
+}
 
-use std::{
-  env,
-  fs::{copy, create_dir_all},
-  panic,
-  path::{Component, Path, PathBuf},
-  sync::OnceLock,
-};
-use tempfile::{Builder, TempDir};
+static SANDBOX: Mutex<Option<PathBuf>> = Mutex::new(None);
 
-pub type Result<T> = std::result::Result<T, eyre::Report>;
+/// The temporary directory the current test is running in, if any
+pub fn sandbox_dir() -> Option<PathBuf> {
+  SANDBOX.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Assert that the current working directory is the test's temporary
+/// directory or somewhere inside of it
+#[macro_export]
+macro_rules! assert_in_sandbox {
+  () => {
+    if let ::std::result::Result::Err(message) = $crate::check_in_sandbox() {
+      ::core::panic!("assertion failed: {}", message);
+    }
+  };
+}
 
 #[doc(hidden)]
-pub static PANIC_HOOK_REPLACE: OnceLock<()> = OnceLock::new();
+pub fn check_in_sandbox() -> std::result::Result<(), String> {
+  let Some(sandbox) = sandbox_dir() else {
+    return Err("not running inside of an assay sandbox".into());
+  };
+  let cwd = env::current_dir().map_err(|e| format!("cannot read the working directory: {e}"))?;
+  let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_owned());
+  if canonical(&cwd).starts_with(canonical(&sandbox)) {
+    Ok(())
+  } else {
+    Err(format!(
+      "working directory is outside of the sandbox\n\n      cwd: {}\n  sandbox: {}",
+      cwd.display(),
+      sandbox.display()
+    ))
+  }
+}
+
+/// Assert that `$path` is `$base` or somewhere inside of it once both are
+/// canonicalized. Paths that don't exist yet are resolved from their closest
+/// existing parent so this works before anything has been written.
+#[macro_export]
+macro_rules! assert_within_dir {
+  ($base:expr, $path:expr $(,)?) => {
+    if let ::std::result::Result::Err(message) = $crate::check_within_dir(
+      ::core::convert::AsRef::<::std::path::Path>::as_ref(&$base),
+      ::core::convert::AsRef::<::std::path::Path>::as_ref(&$path),
+    ) {
+      ::core::panic!("assertion failed: {}", message);
+    }
+  };
+}
+
 #[doc(hidden)]
-pub fn panic_replace() {
-  const HEADER: &str = "ASSAY_PANIC_INTERNAL_MESSAGE\n";
-  PANIC_HOOK_REPLACE.get_or_init(|| {
-    let default = panic::take_hook();
-    panic::set_hook(Box::new(move |panic_info| {
-      let msg = panic_info
-        .payload()
-        .downcast_ref::<&str>()
-        .map(|s| s.to_string())
-        .or_else(|| {
-          panic_info
-            .payload()
-            .downcast_ref::<String>()
-            .map(|s| s.to_owned())
-        })
-        .unwrap_or_default();
-      if let Some(message) = msg.strip_prefix(HEADER) {
-        println!("{}", message.trim());
-      } else {
-        default(panic_info);
+pub fn check_within_dir(base: &Path, path: &Path) -> std::result::Result<(), String> {
+  fn resolve(path: &Path) -> std::result::Result<PathBuf, String> {
+    let absolute = env::current_dir()
+      .map_err(|e| format!("cannot read the working directory: {e}"))?
+      .join(path);
+    for existing in absolute.ancestors() {
+      if let Ok(canonical) = existing.canonicalize() {
+        let rest = absolute.strip_prefix(existing).unwrap_or(Path::new(""));
+        return Ok(PathBuf::from(normalize_path(&canonical.join(rest))));
       }
-    }))
+    }
+    Ok(PathBuf::from(normalize_path(&absolute)))
+  }
+
+  let resolved_base = resolve(base)?;
+  let resolved_path = resolve(path)?;
+  if resolved_path.starts_with(&resolved_base) {
+    Ok(())
+  } else {
+    Err(format!(
+      "`{}` is outside of `{}`\n\n  path: {}\n  base: {}",
+      path.display(),
+      base.display(),
+      resolved_path.display(),
+      resolved_base.display()
+    ))
+  }
+}
+
+/// Assert that the files at `$left` and `$right` have the same contents. On
+/// a mismatch the first byte they differ at and the size of each is shown.
+/// Relative paths are resolved against the current directory.
+#[macro_export]
+macro_rules! assert_files_eq {
+  ($left:expr, $right:expr $(,)?) => {
+    if let ::std::result::Result::Err(message) = $crate::check_files_eq(
+      ::core::convert::AsRef::<::std::path::Path>::as_ref(&$left),
+      ::core::convert::AsRef::<::std::path::Path>::as_ref(&$right),
+    ) {
+      ::core::panic!("assertion failed: {}", message);
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn check_files_eq(left: &Path, right: &Path) -> std::result::Result<(), String> {
+  let read =
+    |path: &Path| std::fs::read(path).map_err(|e| format!("cannot read `{}`: {e}", path.display()));
+  let left_bytes = read(left)?;
+  let right_bytes = read(right)?;
+  if left_bytes == right_bytes {
+    return Ok(());
+  }
+  // When one is a prefix of the other they differ where the shorter one ends
+  let offset = left_bytes
+    .iter()
+    .zip(&right_bytes)
+    .position(|(l, r)| l != r)
+    .unwrap_or_else(|| left_bytes.len().min(right_bytes.len()));
+  Err(format!(
+    "`{}` and `{}` differ starting at byte {offset}\n\n  left: {} bytes\n right: {} bytes",
+    left.display(),
+    right.display(),
+    left_bytes.len(),
+    right_bytes.len()
+  ))
+}
+
+/// Call `$f` and assert that the file at `$path` has the same bytes afterward
+/// as it did before, evaluating to whatever `$f` returned. Text files are shown
+/// as a diff on a change. Relative paths are resolved against the current
+/// directory.
+#[macro_export]
+macro_rules! assert_unchanged {
+  ($path:expr, $f:expr $(,)?) => {
+    match $crate::check_unchanged(
+      ::core::convert::AsRef::<::std::path::Path>::as_ref(&$path),
+      $f,
+    ) {
+      ::std::result::Result::Ok(value) => value,
+      ::std::result::Result::Err(message) => ::core::panic!("assertion failed: {}", message),
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn check_unchanged<R>(path: &Path, f: impl FnOnce() -> R) -> std::result::Result<R, String> {
+  let before = std::fs::read(path).map_err(|e| format!("cannot read `{}`: {e}", path.display()))?;
+  let value = f();
+  let after = match std::fs::read(path) {
+    Ok(after) => after,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+      return Err(format!("`{}` was removed", path.display()));
+    }
+    Err(e) => return Err(format!("cannot read `{}`: {e}", path.display())),
+  };
+  if before == after {
+    return Ok(value);
+  }
+  let sizes = format!(
+    "before: {} bytes\n after: {} bytes",
+    before.len(),
+    after.len()
+  );
+  let changes = match (String::from_utf8(before), String::from_utf8(after)) {
+    (Ok(before), Ok(after)) => {
+      pretty_assertions_sorted::Comparison::new(&Lines(before), &Lines(after)).to_string()
+    }
+    _ => sizes,
+  };
+  Err(format!("`{}` was changed\n\n{changes}", path.display()))
+}
+
+/// Run `f` with `dir` as the working directory, creating it first if needed.
+/// The previous working directory is restored afterwards even if `f` panics.
+pub fn in_dir<R>(dir: impl AsRef<Path>, f: impl FnOnce() -> R) -> Result<R> {
+  struct Restore(PathBuf);
+  impl Drop for Restore {
+    fn drop(&mut self) {
+      let _ = env::set_current_dir(&self.0);
+    }
+  }
+
+  let dir = dir.as_ref();
+  create_dir_all(dir).wrap_err_with(|| format!("cannot create `{}`", dir.display()))?;
+  let restore = Restore(env::current_dir()?);
+  env::set_current_dir(dir).wrap_err_with(|| format!("cannot change to `{}`", dir.display()))?;
+  let value = f();
+  drop(restore);
+  Ok(value)
+}
+
+/// Write `contents` to `name` in the current directory, which is the sandbox
+/// inside of an `#[assay]` test, and return the file's absolute path. Any
+/// parent directories in `name` are created and an existing file is replaced.
+pub fn temp_file_with(name: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<PathBuf> {
+  let path = env::current_dir()?.join(name.as_ref());
+  if let Some(parent) = path.parent() {
+    create_dir_all(parent).wrap_err_with(|| format!("cannot create `{}`", parent.display()))?;
+  }
+  std::fs::write(&path, contents).wrap_err_with(|| format!("cannot write `{}`", path.display()))?;
+  Ok(path)
+}
+
+/// Set `PATH` to exactly `entries`, with relative entries resolved against the
+/// sandbox
+#[doc(hidden)]
+pub fn set_path(entries: &[&str]) -> Result<()> {
+  let base = match sandbox_dir() {
+    Some(dir) => dir,
+    None => env::current_dir()?,
+  };
+  let path =
+    env::join_paths(entries.iter().map(|entry| base.join(entry))).wrap_err("cannot set `PATH`")?;
+  env::set_var("PATH", path);
+  Ok(())
+}
+
+/// Replace the path of the test's temp directory in `text` with `<TMPDIR>`
+fn redact_sandbox(text: String) -> String {
+  match sandbox_dir() {
+    Some(dir) => redact_paths(text, sandbox_paths(&dir)),
+    None => text,
+  }
+}
+
+/// Code under test may report either the path we created or the one the OS
+/// resolves it to (e.g. `/var` vs `/private/var` on macOS)
+fn sandbox_paths(dir: &Path) -> Vec<String> {
+  let mut paths = vec![dir.display().to_string()];
+  if let Ok(canonical) = dir.canonicalize() {
+    paths.push(canonical.display().to_string());
+  }
+  paths
+}
+
+/// Replace each of `paths` in `text` with `<TMPDIR>`
+fn redact_paths(mut text: String, mut paths: Vec<String>) -> String {
+  // The longer of two overlapping paths goes first to avoid leaving a
+  // dangling prefix behind
+  paths.sort_by_key(|p| std::cmp::Reverse(p.len()));
+  for path in paths.iter().filter(|path| !path.is_empty()) {
+    text = text.replace(path, "<TMPDIR>");
+  }
+  text
+}
+
+/// Normalize volatile parts of `text` so it can be compared against a fixed
+/// expected value. The sandbox path is replaced with `<TMPDIR>` and ISO 8601
+/// timestamps with `<TIME>`, then each `(pattern, replacement)` rule is applied
+/// in order. Replacements can refer to capture groups like `$1`.
+///
+/// # Panics
+///
+/// If any of the patterns in `rules` is not a valid regex
+#[cfg(feature = "regex")]
+pub fn redact(text: impl AsRef<str>, rules: &[(&str, &str)]) -> String {
+  use regex::Regex;
+
+  static TIMESTAMP: OnceLock<Regex> = OnceLock::new();
+
+  let mut text = redact_sandbox(text.as_ref().to_owned());
+  let timestamp = TIMESTAMP.get_or_init(|| {
+    Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?").unwrap()
   });
+  text = timestamp.replace_all(&text, "<TIME>").into_owned();
+
+  for (pattern, replacement) in rules {
+    let regex =
+      Regex::new(pattern).unwrap_or_else(|e| panic!("invalid redaction pattern `{pattern}`: {e}"));
+    text = regex.replace_all(&text, *replacement).into_owned();
+  }
+
+  text
+}
+
+/// Assert that `$text` contains a match for the regex `$pattern`. Anchor the
+/// pattern with `^` and `$` to match the whole text.
+#[cfg(feature = "regex")]
+#[macro_export]
+macro_rules! assert_regex_match {
+  ($text:expr, $pattern:expr $(,)?) => {
+    if let ::std::result::Result::Err(message) = $crate::regex_captures(
+      ::core::convert::AsRef::<str>::as_ref(&$text),
+      ::core::convert::AsRef::<str>::as_ref(&$pattern),
+    ) {
+      ::core::panic!("assertion failed: {}", message);
+    }
+  };
+}
+
+/// Assert that `$text` contains a match for the regex `$pattern` and return
+/// its capture groups as a `Vec<Option<String>>`. Like `regex` the first entry
+/// is the whole match and groups that didn't take part in it are `None`.
+#[cfg(feature = "regex")]
+#[macro_export]
+macro_rules! assert_regex_captures {
+  ($text:expr, $pattern:expr $(,)?) => {
+    match $crate::regex_captures(
+      ::core::convert::AsRef::<str>::as_ref(&$text),
+      ::core::convert::AsRef::<str>::as_ref(&$pattern),
+    ) {
+      ::std::result::Result::Ok(captures) => captures,
+      ::std::result::Result::Err(message) => ::core::panic!("assertion failed: {}", message),
+    }
+  };
+}
+
+#[cfg(not(feature = "regex"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! assert_regex_match {
+  ($($tt:tt)*) => {
+    ::core::compile_error!("`assert_regex_match!` needs the `regex` feature of `assay` turned on")
+  };
+}
+
+#[cfg(not(feature = "regex"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! assert_regex_captures {
+  ($($tt:tt)*) => {
+    ::core::compile_error!(
+      "`assert_regex_captures!` needs the `regex` feature of `assay` turned on"
+    )
+  };
+}
+
+#[cfg(feature = "regex")]
+#[doc(hidden)]
+pub fn regex_captures(
+  text: &str,
+  pattern: &str,
+) -> std::result::Result<Vec<Option<String>>, String> {
+  let regex =
+    regex::Regex::new(pattern).map_err(|e| format!("invalid pattern `{pattern}`: {e}"))?;
+  match regex.captures(text) {
+    Some(captures) => Ok(
+      captures
+        .iter()
+        .map(|group| group.map(|m| m.as_str().to_owned()))
+        .collect(),
+    ),
+    None => Err(format!(
+      "text doesn't match `{pattern}`\n\n  text: {text:?}"
+    )),
+  }
+}
+
+/// Run the `std::process::Command` `$cmd` and assert that its stdout and
+/// stderr, interleaved as they were written, match the golden file at
+/// `$golden`. A relative `$golden` is resolved against the crate's
+/// `Cargo.toml` rather than the test's directory. The test's temp directory is
+/// replaced with `<TMPDIR>` in the output first. Run with `ASSAY_BLESS=1` to
+/// write the output to the golden file instead of comparing against it.
+#[macro_export]
+macro_rules! assert_command_golden {
+  ($cmd:expr, $golden:expr $(,)?) => {
+    if let ::std::result::Result::Err(message) = $crate::check_command_golden(
+      &mut $cmd,
+      &::std::path::Path::new(::core::env!("CARGO_MANIFEST_DIR")).join($golden),
+    ) {
+      ::core::panic!("assertion failed: {}", message);
+    }
+  };
+}
+
+#[doc(hidden)]
+pub fn check_command_golden(
+  cmd: &mut std::process::Command,
+  golden: &Path,
+) -> std::result::Result<(), String> {
+  use std::io::{Read, Seek};
+
+  let program = cmd.get_program().to_string_lossy().into_owned();
+  // Both streams share one file so the output stays in the order it was written
+  let mut run = || -> std::io::Result<String> {
+    let mut output = tempfile::tempfile()?;
+    cmd
+      .stdout(output.try_clone()?)
+      .stderr(output.try_clone()?)
+      .status()?;
+    output.rewind()?;
+    let mut bytes = Vec::new();
+    output.read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+  };
+  let actual = redact_sandbox(run().map_err(|e| format!("cannot run `{program}`: {e}"))?);
+  compare_golden(actual, golden, &format!("output of `{program}`"))
+}
+
+/// Compare `actual` against the golden file at `golden`, or write it there
+/// instead when `ASSAY_BLESS` is set
+fn compare_golden(actual: String, golden: &Path, what: &str) -> std::result::Result<(), String> {
+  if env::var_os("ASSAY_BLESS").is_some_and(|bless| !bless.is_empty() && bless != "0") {
+    if let Some(parent) = golden.parent() {
+      create_dir_all(parent).map_err(|e| format!("cannot create `{}`: {e}", parent.display()))?;
+    }
+    return std::fs::write(golden, actual)
+      .map_err(|e| format!("cannot write `{}`: {e}", golden.display()));
+  }
+
+  let expected = std::fs::read_to_string(golden).map_err(|e| {
+    format!(
+      "cannot read golden file `{}`: {e}\n\nrun with `ASSAY_BLESS=1` to create it",
+      golden.display()
+    )
+  })?;
+  if actual == expected {
+    Ok(())
+  } else {
+    Err(format!(
+      "{what} doesn't match `{}`\n\n{}\n\nrun with `ASSAY_BLESS=1` to update it",
+      golden.display(),
+      pretty_assertions_sorted::Comparison::new(&Lines(actual), &Lines(expected))
+    ))
+  }
+}
+
+const STDOUT_BEGIN: &str = "ASSAY_STDOUT_BEGIN";
+const STDOUT_END: &str = "ASSAY_STDOUT_END";
+
+/// Mark where the output `stdout_golden` compares starts, along with the temp
+/// directory's paths so the parent can redact them
+#[doc(hidden)]
+pub fn begin_stdout_golden() {
+  let paths = sandbox_dir()
+    .map(|dir| sandbox_paths(&dir).join("\t"))
+    .unwrap_or_default();
+  // Starts on a new line as libtest may have already printed the test's name
+  println!();
+  println!("{STDOUT_BEGIN}\t{paths}");
+}
+
+/// Mark where the output `stdout_golden` compares ends
+#[doc(hidden)]
+pub fn end_stdout_golden() {
+  println!("{STDOUT_END}");
+}
+
+/// Compare what a test printed between its `stdout_golden` markers against
+/// the golden file at `golden`, with the same redactions as `redact`
+#[doc(hidden)]
+pub fn check_stdout_golden(stdout: &str, golden: &Path) -> std::result::Result<(), String> {
+  let Some((_, rest)) = stdout.split_once(&format!("\n{STDOUT_BEGIN}\t")) else {
+    return Err("cannot find what the test printed to stdout".into());
+  };
+  let (paths, rest) = rest.split_once('\n').unwrap_or((rest, ""));
+  let printed = rest
+    .split_once(STDOUT_END)
+    .map_or(rest, |(printed, _)| printed);
+  let actual = redact_paths(
+    printed.to_owned(),
+    paths.split('\t').map(String::from).collect(),
+  );
+  #[cfg(feature = "regex")]
+  let actual = redact(actual, &[]);
+  compare_golden(actual, golden, "stdout of the test")
+}
+
+/// Run a block of code inside its own temporary directory without needing an
+/// `#[assay]` test, e.g. in doc tests, examples, or benchmarks. The block can
+/// use `?` and evaluates to an `assay::Result` of its final expression.
+/// Environment variables can be set for the block with a leading
+/// `env = [...];`. Afterwards the working directory and any variables that
+/// were set are put back the way they were.
+///
+/// Unlike `#[assay]` this runs in the current process, so a block shouldn't
+/// run at the same time as anything else that depends on the working
+/// directory or environment.
+///
+/// ```
+/// use std::{env, fs, path::Path};
+///
+/// let contents = assay::isolated! {
+///   env = [("GREETING", "hello")];
+///   fs::write("greeting.txt", env::var("GREETING")?)?;
+///   fs::read_to_string("greeting.txt")?
+/// }
+/// .unwrap();
+///
+/// assert_eq!(contents, "hello");
+/// assert!(!Path::new("greeting.txt").exists());
+/// assert!(env::var("GREETING").is_err());
+/// ```
+#[macro_export]
+macro_rules! isolated {
+  (env = [$(($key:expr, $value:expr)),* $(,)?]; $($body:tt)*) => {
+    $crate::run_isolated(&[$(($key, $value)),*], || Ok({ $($body)* }))
+  };
+  ($($body:tt)*) => {
+    $crate::run_isolated(&[], || Ok({ $($body)* }))
+  };
+}
+
+#[doc(hidden)]
+pub fn expand_env(value: &str) -> String {
+  // `$NAME` and `${NAME}` are replaced with the variable's current value, or
+  // nothing if it isn't set, and `$$` is a literal `$`
+  let mut out = String::with_capacity(value.len());
+  let mut rest = value;
+  while let Some(start) = rest.find('$') {
+    out.push_str(&rest[..start]);
+    rest = &rest[start + 1..];
+    let (name, after) = if let Some(braced) = rest.strip_prefix('{') {
+      match braced.find('}') {
+        Some(end) => (&braced[..end], &braced[end + 1..]),
+        None => ("", rest),
+      }
+    } else if let Some(after) = rest.strip_prefix('$') {
+      out.push('$');
+      rest = after;
+      continue;
+    } else {
+      let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+      (&rest[..end], &rest[end..])
+    };
+    if name.is_empty() {
+      out.push('$');
+    } else {
+      out.push_str(&env::var(name).unwrap_or_default());
+      rest = after;
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+#[doc(hidden)]
+pub fn run_isolated<T>(vars: &[(&str, &str)], f: impl FnOnce() -> Result<T>) -> Result<T> {
+  let previous_sandbox = sandbox_dir();
+  let fs = PrivateFS::new()?;
+  let previous_vars = vars
+    .iter()
+    .map(|(key, value)| {
+      let previous = env::var_os(key);
+      env::set_var(key, value);
+      (*key, previous)
+    })
+    .collect::<Vec<_>>();
+
+  let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+
+  for (key, previous) in previous_vars.into_iter().rev() {
+    match previous {
+      Some(value) => env::set_var(key, value),
+      None => env::remove_var(key),
+    }
+  }
+  env::set_current_dir(&fs.ran_from)?;
+  *SANDBOX.lock().unwrap_or_else(|e| e.into_inner()) = previous_sandbox;
+  drop(fs);
+
+  result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+static KEPT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Removes the temp dir `keep_on_failure` left behind once the test is over
+/// unless it failed, in which case where it is gets printed instead
+#[doc(hidden)]
+pub struct KeptDir {
+  should_panic: bool,
+  pub failed: bool,
+}
+
+impl KeptDir {
+  pub fn new(should_panic: bool) -> Self {
+    Self {
+      should_panic,
+      failed: false,
+    }
+  }
+
+  /// For tests without `keep_on_failure`, which only keep their temp dir when
+  /// `ASSAY_KEEP_TEMP=1` is set
+  pub fn from_env(should_panic: bool) -> Option<Self> {
+    keep_temp_from_env().then(|| Self::new(should_panic))
+  }
+}
+
+fn keep_temp_from_env() -> bool {
+  env::var("ASSAY_KEEP_TEMP").is_ok_and(|s| s == "1")
+}
+
+impl Drop for KeptDir {
+  fn drop(&mut self) {
+    let Some(dir) = KEPT.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+      return;
+    };
+    let panicked = std::thread::panicking();
+    let failed = if self.should_panic {
+      !panicked
+    } else {
+      panicked || self.failed
+    };
+    if failed {
+      eprintln!("assay: kept temp dir of failed test at {}", dir.display());
+    } else {
+      let _ = std::fs::remove_dir_all(dir);
+    }
+  }
+}
+
+/// How a file should be included into the temp file system
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IncludeOptions {
+  /// Make the file read-only once it's been copied
+  pub readonly: bool,
+  /// Fail rather than replace a file that's already at the destination
+  pub no_overwrite: bool,
 }
 
 #[doc(hidden)]
@@ -72,64 +2459,442 @@ impl PrivateFS {
     let ran_from = env::current_dir()?;
     let directory = Builder::new().prefix("private").tempdir()?;
     env::set_current_dir(directory.path())?;
+    *SANDBOX.lock().unwrap_or_else(|e| e.into_inner()) = Some(directory.path().to_owned());
     Ok(Self {
       ran_from,
       directory,
     })
   }
 
+  /// Leave the directory behind if the test fails so it can be looked at
+  /// afterwards. `ASSAY_KEEP_TEMP=1` turns this on for every test
+  pub fn keep_on_failure(&mut self, keep: bool) {
+    if keep || keep_temp_from_env() {
+      self.directory.disable_cleanup(true);
+      *KEPT.lock().unwrap_or_else(|e| e.into_inner()) = Some(self.directory.path().to_owned());
+    }
+  }
+
   pub fn include(&self, path: impl AsRef<Path>) -> Result<()> {
-    // Get our pathbuf to the file to include
-    let mut inner_path = path.as_ref().to_owned();
+    let path = path.as_ref();
+    self.include_as(path, include_dest(path))
+  }
+
+  /// Include a file and make it read-only so that writing to it fails
+  pub fn include_readonly(&self, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    self.include_as_readonly(path, include_dest(path))
+  }
+
+  pub fn include_as(&self, path: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+    self.copy_in(path.as_ref(), dest.as_ref(), IncludeOptions::default())
+  }
+
+  /// Include a file at `dest` and make it read-only so that writing to it fails
+  pub fn include_as_readonly(&self, path: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+    let options = IncludeOptions {
+      readonly: true,
+      ..IncludeOptions::default()
+    };
+    self.copy_in(path.as_ref(), dest.as_ref(), options)
+  }
+
+  /// Include a file at `dest`, or the same relative path if there's no `dest`,
+  /// with any combination of options. Files matched by a pattern with no `dest`
+  /// go at the root of the temp file system instead
+  #[doc(hidden)]
+  pub fn include_with(
+    &self,
+    path: impl AsRef<Path>,
+    dest: Option<&str>,
+    options: IncludeOptions,
+  ) -> Result<()> {
+    let path = path.as_ref();
+    let dest = dest.map_or_else(|| include_dest(path), PathBuf::from);
+    self.copy_in(path, &dest, options)
+  }
+
+  /// Copy the directory `path` and everything in it into the temp file system
+  /// at `dest`
+  pub fn include_dir(&self, path: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+    let inner_path = self.ran_from.join(path.as_ref());
+    if inner_path.is_file() {
+      bail!("cannot include `{}`: not a directory", inner_path.display());
+    }
+    if !inner_path.is_dir() {
+      bail!(
+        "cannot include `{}`: directory not found",
+        inner_path.display()
+      );
+    }
+    self.copy_dir_in(&inner_path, dest.as_ref())
+  }
+
+  /// Include a directory at `dest`, or the same relative path if there's no
+  /// `dest`
+  #[doc(hidden)]
+  pub fn include_dir_with(&self, path: impl AsRef<Path>, dest: Option<&str>) -> Result<()> {
+    let path = path.as_ref();
+    let dest = dest.map_or_else(|| default_dest(path), PathBuf::from);
+    self.include_dir(path, dest)
+  }
+
+  /// Write `contents` to a new file at `path` in the temp file system,
+  /// replacing anything already there. `path` has to be relative and can't go
+  /// up with `..` so the file can't end up outside of the temp file system.
+  pub fn create_file(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let relative = path.as_ref();
+    if relative
+      .components()
+      .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+      bail!(
+        "cannot create `{}`: the path must be relative and cannot contain `..`",
+        relative.display()
+      );
+    }
+    let target = self.directory.path().join(relative);
+    if let Some(parent) = target.parent() {
+      create_dir_all(parent)?;
+    }
+    std::fs::write(&target, contents)
+      .wrap_err_with(|| format!("cannot create `{}`", relative.display()))
+  }
+
+  /// Set an env var for every `KEY=value` line in the file at `path`, which is
+  /// relative to where the test was run from. Blank lines and ones starting
+  /// with `#` are skipped, and a value can be wrapped in quotes.
+  #[doc(hidden)]
+  pub fn load_env_file(&self, path: impl AsRef<Path>) -> Result<()> {
+    let path = self.ran_from.join(path.as_ref());
+    let contents = std::fs::read_to_string(&path)
+      .wrap_err_with(|| format!("cannot read env file `{}`", path.display()))?;
+    for (number, line) in contents.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let invalid =
+        |message: &str| eyre::eyre!("`{}` line {}: {message}", path.display(), number + 1);
+      let (key, value) = line
+        .split_once('=')
+        .ok_or_else(|| invalid("expected `KEY=value`"))?;
+      let key = key.trim();
+      let value = value.trim();
+      let value = match value.as_bytes() {
+        [b'"', .., b'"'] | [b'\'', .., b'\''] => &value[1..value.len() - 1],
+        _ => value,
+      };
+      // `std::env::set_var` panics on these
+      if key.is_empty() || key.contains('\0') {
+        return Err(invalid(
+          "env var names cannot be empty or contain a NUL character",
+        ));
+      }
+      if value.contains('\0') {
+        return Err(invalid("env var values cannot contain a NUL character"));
+      }
+      env::set_var(key, value);
+    }
+    Ok(())
+  }
+
+  /// Symlink `path` into the temp file system at `dest` rather than copying
+  /// it, which is much faster for big files. Anything written through the
+  /// link changes the original so this is meant for files the test only
+  /// reads. On Windows, where making symlinks needs extra privileges, the file
+  /// is copied if the link can't be made.
+  pub fn link(&self, path: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+    let (inner_path, relative, target) = self.resolve(path.as_ref(), dest.as_ref(), "link")?;
+    if target.exists() {
+      bail!(
+        "cannot link `{}` as `{}`: destination already exists",
+        inner_path.display(),
+        relative.display()
+      );
+    }
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(&inner_path, &target);
+    #[cfg(windows)]
+    let linked = std::os::windows::fs::symlink_file(&inner_path, &target)
+      .or_else(|_| copy(&inner_path, &target).map(drop));
+    #[cfg(not(any(unix, windows)))]
+    let linked = copy(&inner_path, &target).map(drop);
+    linked.wrap_err_with(|| {
+      format!(
+        "cannot link `{}` as `{}`",
+        inner_path.display(),
+        relative.display()
+      )
+    })
+  }
+
+  /// Link a file at `dest`, or the same relative path if there's no `dest`
+  #[doc(hidden)]
+  pub fn link_with(&self, path: impl AsRef<Path>, dest: Option<&str>) -> Result<()> {
+    let path = path.as_ref();
+    let dest = dest.map_or_else(|| default_dest(path), PathBuf::from);
+    self.link(path, dest)
+  }
 
+  /// Work out where `path` is on the real file system and where it'll go in
+  /// the temp one, creating any parent directories it needs there
+  fn resolve(&self, path: &Path, dest: &Path, action: &str) -> Result<(PathBuf, PathBuf, PathBuf)> {
     // If the path given is not absolute then it's relative to the dir we
     // ran the test from
-    let is_relative = inner_path.is_relative();
-    if is_relative {
-      inner_path = self.ran_from.join(&path);
+    let inner_path = self.ran_from.join(path);
+    if !inner_path.is_file() {
+      bail!("cannot {action} `{}`: file not found", inner_path.display());
     }
 
+    // A destination ending in a separator is a directory to put the file in
+    // rather than the path to copy the file to
+    let is_dir = dest
+      .as_os_str()
+      .to_string_lossy()
+      .ends_with(['/', std::path::MAIN_SEPARATOR]);
+    let relative = match inner_path.file_name() {
+      Some(file_name) if is_dir => dest.join(file_name),
+      _ => dest.to_owned(),
+    };
+
     // Get our working directory
     let dir = self.directory.path().to_owned();
 
-    // Make the relative path of the file in relation to our temp file
-    // system based on if it was absolute or not
-    let relative = if !is_relative {
-      inner_path
-        .components()
-        .filter(|c| *c != Component::RootDir)
-        .collect::<PathBuf>()
-    } else {
-      path.as_ref().into()
-    };
-
     // If the relative path to the file includes parent directories create
     // them
     if let Some(parent) = relative.parent() {
       create_dir_all(dir.join(parent))?;
     }
 
+    let target = dir.join(&relative);
+    Ok((inner_path, relative, target))
+  }
+
+  fn copy_dir_in(&self, dir: &Path, dest: &Path) -> Result<()> {
+    let target = self.directory.path().join(dest);
+    create_dir_all(&target).wrap_err_with(|| format!("cannot create `{}`", dest.display()))?;
+    let entries =
+      std::fs::read_dir(dir).wrap_err_with(|| format!("cannot read `{}`", dir.display()))?;
+    for entry in entries {
+      let entry = entry?;
+      let path = entry.path();
+      let dest = dest.join(entry.file_name());
+      // The entry's own type rather than what it points to, so a symlink to a
+      // directory above it can't make this recurse forever
+      let file_type = entry.file_type()?;
+      if file_type.is_symlink() {
+        self.copy_link_in(&path, &dest)?;
+      } else if file_type.is_dir() {
+        self.copy_dir_in(&path, &dest)?;
+      } else {
+        self.copy_file_in(&path, &dest, IncludeOptions::default())?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Make a symlink at `dest` pointing to the same place as the one at `path`
+  /// rather than copying whatever it points to
+  fn copy_link_in(&self, path: &Path, dest: &Path) -> Result<()> {
+    let link = std::fs::read_link(path)
+      .wrap_err_with(|| format!("cannot read the link `{}`", path.display()))?;
+    let target = self.directory.path().join(dest);
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(&link, &target);
+    #[cfg(windows)]
+    let linked = if path.is_dir() {
+      std::os::windows::fs::symlink_dir(&link, &target)
+    } else {
+      std::os::windows::fs::symlink_file(&link, &target)
+    };
+    #[cfg(not(any(unix, windows)))]
+    let linked = Ok::<_, std::io::Error>(());
+    linked.wrap_err_with(|| {
+      format!(
+        "cannot include the link `{}` as `{}`",
+        path.display(),
+        dest.display()
+      )
+    })
+  }
+
+  fn copy_in(&self, path: &Path, dest: &Path, options: IncludeOptions) -> Result<()> {
+    if !is_pattern(path) {
+      return self.copy_file_in(path, dest, options);
+    }
+
+    let inner_path = self.ran_from.join(path);
+    let (Some(parent), Some(pattern)) = (inner_path.parent(), inner_path.file_name()) else {
+      bail!("cannot include `{}`: file not found", inner_path.display());
+    };
+    if parent.to_string_lossy().contains(['*', '?']) {
+      bail!(
+        "cannot include `{}`: only the file name can contain `*` or `?`",
+        inner_path.display()
+      );
+    }
+    let pattern = pattern.to_string_lossy();
+    let mut matches = match std::fs::read_dir(parent) {
+      Ok(entries) => entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+          path
+            .file_name()
+            .is_some_and(|name| wildcard_match(&pattern, &name.to_string_lossy()))
+        })
+        .collect::<Vec<_>>(),
+      Err(_) => Vec::new(),
+    };
+    if matches.is_empty() {
+      bail!("cannot include `{}`: file not found", inner_path.display());
+    }
+    matches.sort();
+
+    // Each match takes the place of the pattern in the destination, or goes in
+    // the destination if it's a directory
+    for path in matches {
+      let name = path.file_name().unwrap_or_default();
+      let dest = if is_pattern(dest) {
+        dest.with_file_name(name)
+      } else {
+        dest.join(name)
+      };
+      self.copy_file_in(&path, &dest, options)?;
+    }
+    Ok(())
+  }
+
+  fn copy_file_in(&self, path: &Path, dest: &Path, options: IncludeOptions) -> Result<()> {
     // Copy the file over from the file system into the temp file system
-    copy(inner_path, dir.join(relative))?;
+    let (inner_path, relative, target) = self.resolve(path, dest, "include")?;
+    if options.no_overwrite && target.exists() {
+      bail!(
+        "cannot include `{}` as `{}`: destination already exists",
+        inner_path.display(),
+        relative.display()
+      );
+    }
+    copy(&inner_path, &target).wrap_err_with(|| {
+      format!(
+        "cannot include `{}` as `{}`",
+        inner_path.display(),
+        relative.display()
+      )
+    })?;
+
+    // Clears the write bits on Unix and sets the readonly attribute on Windows
+    if options.readonly {
+      let mut permissions = metadata(&target)?.permissions();
+      permissions.set_readonly(true);
+      set_permissions(&target, permissions)
+        .wrap_err_with(|| format!("cannot make `{}` read-only", relative.display()))?;
+    }
 
     Ok(())
   }
 }
 
+// `wildcard_match`, which is shared with `for_each_file` in the proc macro
+assay_proc_macro::__shared!();
+
+/// Whether the file name in `path` has a `*` or `?` in it to match many files
+fn is_pattern(path: &Path) -> bool {
+  path
+    .file_name()
+    .is_some_and(|name| name.to_string_lossy().contains(['*', '?']))
+}
+
+/// Where `include` puts a file if no destination is given. Files matching a
+/// pattern go at the root of the temp file system under their own names,
+/// anything else goes where `default_dest` puts it
+fn include_dest(path: &Path) -> PathBuf {
+  match path.file_name() {
+    Some(name) if is_pattern(path) => PathBuf::from(name),
+    _ => default_dest(path),
+  }
+}
+
+/// Where a file is included to in the temp file system if no destination is
+/// given, which is the same relative path it has from where the test was run
+fn default_dest(path: &Path) -> PathBuf {
+  if path.is_absolute() {
+    path
+      .components()
+      .filter(|c| *c != Component::RootDir)
+      .collect::<PathBuf>()
+  } else {
+    path.to_owned()
+  }
+}
+
 // Async functionality
+#[cfg(feature = "async-tokio-runtime")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mock_time_async {
+  () => {};
+}
+
+#[cfg(not(feature = "async-tokio-runtime"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mock_time_async {
+  () => {
+    ::core::compile_error!(
+      "`mock_time` can't be used on `async` tests with `async-std` since it can't pause time, use the `async-tokio-runtime` feature or make the test sync"
+    )
+  };
+}
+
 #[doc(hidden)]
 #[cfg(any(feature = "async-tokio-runtime", feature = "async-std-runtime"))]
 pub mod async_runtime {
   use super::Result;
   use std::future::Future;
+
+  /// Settings for the runtime a test's future is driven to completion on
+  #[derive(Default)]
+  pub struct Config {
+    /// Start with time paused for `#[assay(mock_time)]`
+    pub paused: bool,
+    /// Stack size for any worker threads the runtime spawns
+    pub thread_stack_size: Option<usize>,
+    /// Name for any worker threads the runtime spawns
+    pub thread_name: Option<&'static str>,
+  }
+
   pub struct Runtime;
   impl Runtime {
-    #[cfg(feature = "async-tokio-runtime")]
     pub fn block_on<F: Future>(fut: F) -> Result<F::Output> {
-      Ok(tokio::runtime::Runtime::new()?.block_on(fut))
+      Self::block_on_with(fut, Config::default())
+    }
+    #[cfg(feature = "async-tokio-runtime")]
+    pub fn block_on_with<F: Future>(fut: F, config: Config) -> Result<F::Output> {
+      // tokio only allows pausing time on a current thread runtime, after
+      // which it automatically advances whenever the runtime would otherwise
+      // sit idle waiting on a timer
+      let mut builder = if config.paused {
+        let mut builder = tokio::runtime::Builder::new_current_thread();
+        builder.start_paused(true);
+        builder
+      } else {
+        tokio::runtime::Builder::new_multi_thread()
+      };
+      builder.enable_all();
+      if let Some(size) = config.thread_stack_size {
+        builder.thread_stack_size(size);
+      }
+      if let Some(name) = config.thread_name {
+        builder.thread_name(name);
+      }
+      Ok(builder.build()?.block_on(fut))
     }
+    // `paused` is never set here since `mock_time` on an `async` test is a
+    // compile error with async-std
     #[cfg(feature = "async-std-runtime")]
-    pub fn block_on<F: Future>(fut: F) -> Result<F::Output> {
+    pub fn block_on_with<F: Future>(fut: F, _: Config) -> Result<F::Output> {
       Ok(async_std::task::block_on(fut))
     }
   }
@@ -1,7 +1,12 @@
 //! Traits and types made to make testing with network services/code much easer
 
-use std::io;
-use std::net::{TcpListener, UdpSocket};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
+use std::thread::{self, JoinHandle};
 
 /// Generate a bound address with either ipv4 or ipv6 that won't conflict with other addresses
 pub trait TestAddress
@@ -31,3 +36,90 @@ impl TestAddress for UdpSocket {
     Self::bind(("::", 0))
   }
 }
+
+/// One step of the script a [`mock_server`] follows with its client
+pub enum Reply {
+  /// Send these bytes without waiting for the client
+  Push(Vec<u8>),
+  /// Wait for the client to send something and answer with these bytes
+  Respond(Vec<u8>),
+  /// Wait for the client to send something and send it straight back
+  Echo,
+}
+
+/// A handle to the background thread started by [`mock_server`]
+pub struct MockServer {
+  addr: SocketAddr,
+  stopped: Arc<AtomicBool>,
+  handle: JoinHandle<io::Result<Vec<u8>>>,
+}
+
+impl MockServer {
+  /// The address clients should connect to
+  pub fn addr(&self) -> SocketAddr {
+    self.addr
+  }
+
+  /// Wait for the server to finish its script and get back every byte the
+  /// client sent it
+  pub fn join(self) -> io::Result<Vec<u8>> {
+    self
+      .handle
+      .join()
+      .unwrap_or_else(|e| std::panic::resume_unwind(e))
+  }
+
+  /// Stop the server if no client has connected yet, otherwise wait for the
+  /// current connection to finish like [`MockServer::join`]
+  pub fn stop(self) -> io::Result<Vec<u8>> {
+    self.stopped.store(true, Ordering::SeqCst);
+    // Wake up the thread if it's still waiting in `accept`
+    let _ = TcpStream::connect(self.addr);
+    self.join()
+  }
+}
+
+/// Start a TCP server on a free local port that accepts a single connection
+/// and works through `responses` in order before closing it. The connection is
+/// also closed early if the client hangs up.
+pub fn mock_server(responses: impl IntoIterator<Item = Reply>) -> io::Result<MockServer> {
+  let listener = TcpListener::test_v4()?;
+  let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, listener.local_addr()?.port()));
+  let responses = responses.into_iter().collect::<Vec<_>>();
+  let stopped = Arc::new(AtomicBool::new(false));
+  let handle = thread::spawn({
+    let stopped = stopped.clone();
+    move || {
+      let (mut stream, _) = listener.accept()?;
+      let mut received = Vec::new();
+      if stopped.load(Ordering::SeqCst) {
+        return Ok(received);
+      }
+      let mut buf = [0; 4096];
+      for reply in responses {
+        let read = match reply {
+          Reply::Push(_) => None,
+          Reply::Respond(_) | Reply::Echo => match stream.read(&mut buf)? {
+            0 => break,
+            n => Some(n),
+          },
+        };
+        if let Some(n) = read {
+          received.extend_from_slice(&buf[..n]);
+        }
+        match reply {
+          Reply::Push(bytes) | Reply::Respond(bytes) => stream.write_all(&bytes)?,
+          Reply::Echo => stream.write_all(&buf[..read.unwrap_or(0)])?,
+        }
+      }
+      let _ = stream.shutdown(Shutdown::Write);
+      stream.read_to_end(&mut received)?;
+      Ok(received)
+    }
+  });
+  Ok(MockServer {
+    addr,
+    stopped,
+    handle,
+  })
+}
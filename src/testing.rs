@@ -0,0 +1,30 @@
+//! Helpers for checking how failing tests get reported, by running tests that
+//! are `#[assay(ignore)]`d on purpose and looking at their output
+
+use std::process::{Command, Output};
+
+/// Run `cargo test --workspace -- --ignored <name>` and capture its output.
+/// Anything matching `name` is run, so pick names for the ignored tests that
+/// don't contain one another.
+pub fn run_ignored(name: &str) -> Output {
+  ignored_command(name).output().expect("ran cargo test")
+}
+
+/// The command [`run_ignored`] runs, for when more arguments or environment
+/// variables are needed. Arguments added to it go to the test harness.
+pub fn ignored_command(name: &str) -> Command {
+  let mut command = cargo_test();
+  command.args(["--workspace", "--", "--ignored", name]);
+  command
+}
+
+/// A bare `cargo test` command using the same `cargo` as the current run, for
+/// running tests in other ways than [`run_ignored`] does.
+///
+/// `NEXTEST_EXECUTION_MODE` is removed for the new run as otherwise every test
+/// in it would think it was already running in its own process.
+pub fn cargo_test() -> Command {
+  let mut command = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()));
+  command.arg("test").env_remove("NEXTEST_EXECUTION_MODE");
+  command
+}
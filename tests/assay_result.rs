@@ -1,8 +1,8 @@
-use assay::assay;
 use assay::eyre::bail;
+use assay::testing::run_ignored;
+use assay::{assay, Checks};
 use std::error::Error;
 use std::fmt::Display;
-use std::process::{Command, Stdio};
 
 #[assay(ignore)]
 fn result_output_test() {
@@ -26,18 +26,7 @@ fn result_bail_test() {
 
 #[test]
 fn check_result_output() {
-  let output = Command::new("cargo")
-    .args([
-      "test",
-      "--workspace",
-      "--",
-      "--ignored",
-      "result_output_test",
-    ])
-    .stdout(Stdio::piped())
-    .stderr(Stdio::piped())
-    .output()
-    .unwrap();
+  let output = run_ignored("result_output_test");
   let check_result = String::from_utf8(output.stdout).unwrap();
   let compare = [
     "---- result_output_test stdout ----",
@@ -54,12 +43,7 @@ fn check_result_output() {
 
 #[test]
 fn check_bail_output() {
-  let output = Command::new("cargo")
-    .args(["test", "--workspace", "--", "--ignored", "result_bail_test"])
-    .stdout(Stdio::piped())
-    .stderr(Stdio::piped())
-    .output()
-    .unwrap();
+  let output = run_ignored("result_bail_test");
   let check_result = String::from_utf8(output.stdout).unwrap();
   let compare = [
     "---- result_bail_test stdout ----",
@@ -73,3 +57,57 @@ fn check_bail_output() {
     }
   }
 }
+
+#[assay]
+fn checks_pass() {
+  let mut checks = Checks::new();
+  checks
+    .check(1 + 1 == 2, "math works")
+    .check(true, "true is true");
+  checks.finish()?;
+}
+
+#[assay]
+fn checks_collect_failures() {
+  let mut checks = Checks::new();
+  checks
+    .check(false, "first failure")
+    .check(true, "not a failure")
+    .check(false, format!("second failure: {}", 42));
+  let error = checks.finish().unwrap_err().to_string();
+  assert_eq!(
+    error,
+    "2 of 3 checks failed\n\n\
+     first failure\n  at tests/assay_result.rs:74:6\n\n\
+     second failure: 42\n  at tests/assay_result.rs:76:6"
+  );
+}
+
+fn failing_setup() -> assay::Result<()> {
+  bail!("could not create the database")
+}
+
+fn failing_teardown() -> assay::Result<()> {
+  bail!("could not drop the database")
+}
+
+#[assay(ignore, setup = failing_setup()?)]
+fn labeled_setup_failure() {}
+
+#[assay(ignore, teardown = failing_teardown()?)]
+fn labeled_teardown_failure() {}
+
+#[test]
+fn check_labeled_setup_and_teardown_output() {
+  let output = run_ignored("labeled_");
+  let check_result = String::from_utf8(output.stdout).unwrap();
+  let compare = [
+    "---- labeled_setup_failure stdout ----\nError: setup failed\n\nCaused by:\n    could not create the database",
+    "---- labeled_teardown_failure stdout ----\nError: teardown failed\n\nCaused by:\n    could not drop the database",
+  ];
+  for line in compare {
+    if !check_result.contains(line) {
+      panic!("Unexpected output for setup and teardown.\n\nOutput:\n{check_result}");
+    }
+  }
+}
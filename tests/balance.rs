@@ -0,0 +1,82 @@
+use assay::{assay, assert_balanced, testing::run_ignored, BalanceTracker};
+use std::{sync::Arc, thread};
+
+struct Guard(Arc<BalanceTracker>);
+
+impl Guard {
+  fn new(tracker: &Arc<BalanceTracker>) -> Self {
+    tracker.acquire();
+    Self(tracker.clone())
+  }
+}
+
+impl Drop for Guard {
+  fn drop(&mut self) {
+    self.0.release();
+  }
+}
+
+#[assay]
+fn acquires_released() {
+  let tracker = BalanceTracker::new();
+  assert_balanced!(tracker);
+  tracker.acquire();
+  tracker.acquire();
+  assert_eq!(tracker.outstanding(), 2);
+  tracker.release();
+  tracker.release();
+  assert_balanced!(tracker);
+}
+
+#[assay]
+fn guards_released_across_threads() {
+  let tracker = Arc::new(BalanceTracker::new());
+  let handles = (0..4)
+    .map(|_| {
+      let tracker = tracker.clone();
+      thread::spawn(move || {
+        let _outer = Guard::new(&tracker);
+        let _inner = Guard::new(&tracker);
+      })
+    })
+    .collect::<Vec<_>>();
+  for handle in handles {
+    handle.join().unwrap();
+  }
+  assert_balanced!(tracker);
+}
+
+#[assay(ignore)]
+fn unbalanced_leaked_guard() {
+  let tracker = Arc::new(BalanceTracker::new());
+  let _kept = Guard::new(&tracker);
+  std::mem::forget(Guard::new(&tracker));
+  drop(Guard::new(&tracker));
+  assert_balanced!(tracker);
+}
+
+#[assay(ignore)]
+fn unbalanced_extra_release() {
+  let tracker = BalanceTracker::new();
+  tracker.release();
+  assert_eq!(tracker.outstanding(), -1);
+  assert_balanced!(tracker);
+}
+
+#[test]
+fn unbalanced_output() {
+  let output = run_ignored("unbalanced_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- unbalanced_leaked_guard stdout ----",
+    "assertion failed: `tracker` is not balanced\n\nacquired: 3\nreleased: 1\n\n2 acquired but never released",
+    "---- unbalanced_extra_release stdout ----",
+    "assertion failed: `tracker` is not balanced\n\nacquired: 0\nreleased: 1\n\n1 released without being acquired",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for balance assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,85 @@
+use assay::{
+  assay,
+  testing::{cargo_test, run_ignored},
+  TestFixture,
+};
+use std::{fs, thread, time::Duration};
+
+#[assay(bench, iterations = 10)]
+fn bench_prints_timings() {
+  fs::write("iteration", "ran")?;
+}
+
+#[assay(bench, iterations = 5, max_median = "1s")]
+fn bench_under_max_median() {
+  thread::sleep(Duration::from_millis(1));
+}
+
+struct Counter(usize);
+
+impl TestFixture for Counter {
+  fn setup() -> assay::Result<Self> {
+    Ok(Self(0))
+  }
+
+  fn teardown(self) -> assay::Result<()> {
+    assert_eq!(self.0, 20);
+    Ok(())
+  }
+}
+
+#[assay(fixture = Counter, iterations = 20)]
+fn bench_with_fixture() {
+  fixture.0 += 1;
+}
+
+#[assay(ignore, iterations = 3, max_median = "1ms")]
+fn bench_over_max_median() {
+  thread::sleep(Duration::from_millis(20));
+}
+
+#[test]
+fn bench_output() {
+  let output = cargo_test()
+    .args([
+      "--test",
+      "bench",
+      "--",
+      "--show-output",
+      "--exact",
+      "bench_prints_timings",
+    ])
+    .output()
+    .unwrap();
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "test bench_prints_timings ... ok",
+    "bench bench_prints_timings: 10 iterations",
+    "     min: ",
+    "  median: ",
+    "     max: ",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for benchmarks.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[test]
+fn bench_over_max_median_output() {
+  let output = run_ignored("bench_over_max_median");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- bench_over_max_median stdout ----",
+    "bench bench_over_max_median: 3 iterations",
+    "is slower than `max_median` of 1ms",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for benchmarks.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,64 @@
+use assay::{assay, assert_closed, testing::run_ignored};
+use std::{sync::mpsc, thread};
+
+#[assay]
+fn closed_channel() {
+  let (tx, mut rx) = mpsc::channel();
+  thread::spawn(move || tx.send(1).unwrap()).join().unwrap();
+  assert_eq!(rx.recv()?, 1);
+  assert_closed!(rx);
+}
+
+#[assay(ignore)]
+fn channel_closed_but_not_drained() {
+  let (tx, mut rx) = mpsc::channel();
+  tx.send(1)?;
+  drop(tx);
+  assert_closed!(rx);
+}
+
+#[assay(ignore)]
+fn channel_closed_while_open() {
+  let (_tx, mut rx) = mpsc::channel::<u8>();
+  assert_closed!(rx);
+}
+
+#[cfg(feature = "async-tokio-runtime")]
+#[assay]
+async fn closed_tokio_channels() {
+  let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+  tokio::spawn(async move { tx.send(1).await.unwrap() }).await?;
+  assert_eq!(rx.recv().await, Some(1));
+  assert_closed!(rx);
+
+  let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<u8>();
+  drop(tx);
+  assert_closed!(rx);
+}
+
+#[cfg(feature = "async-tokio-runtime")]
+#[assay(ignore)]
+async fn channel_closed_while_tokio_open() {
+  let (_tx, mut rx) = tokio::sync::mpsc::channel::<u8>(1);
+  assert_closed!(rx);
+}
+
+#[test]
+fn channel_closed_output() {
+  let output = run_ignored("channel_closed_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- channel_closed_but_not_drained stdout ----",
+    "assertion failed: channel still had a value waiting to be received",
+    "---- channel_closed_while_open stdout ----",
+    "assertion failed: channel is still open",
+    #[cfg(feature = "async-tokio-runtime")]
+    "---- channel_closed_while_tokio_open stdout ----",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for channel assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
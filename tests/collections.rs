@@ -0,0 +1,291 @@
+use assay::{
+  assay, assert_decreasing, assert_eq_unordered, assert_increasing, assert_ordered,
+  assert_stable_sort, assert_subset, assert_superset, testing::run_ignored,
+};
+use std::collections::HashSet;
+
+#[assay]
+fn subset_and_superset() {
+  let actual = vec!["read", "write", "admin"];
+  assert_subset!(["read", "write"], actual.clone());
+  assert_superset!(["read", "write", "admin", "owner"], actual);
+
+  let ids: HashSet<u32> = (0..10).collect();
+  assert_subset!(&[1, 5, 9], &ids);
+  assert_subset!(Vec::<u32>::new(), ids);
+
+  let roles = ["read", "write"];
+  let granted = |expected: &[&str]| assert_subset!(expected, &roles);
+  granted(&["write"]);
+  let covers = |actual: &[&str]| assert_superset!(&roles, actual);
+  covers(&["read"]);
+}
+
+#[assay(ignore)]
+fn subset_with_missing_items() {
+  assert_subset!(["read", "write", "delete"], ["read", "admin"]);
+}
+
+#[assay(ignore)]
+fn superset_with_missing_items() {
+  assert_superset!([1, 2], [1, 2, 3, 4]);
+}
+
+#[test]
+fn missing_items_output() {
+  let output = run_ignored("with_missing_items");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- subset_with_missing_items stdout ----",
+    "assertion failed: `expected` is not a subset of `actual`",
+    "missing from actual: [\n    \"write\",\n    \"delete\",\n]",
+    "---- superset_with_missing_items stdout ----",
+    "assertion failed: `expected` is not a superset of `actual`",
+    "missing from expected: [\n    3,\n    4,\n]",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for collection assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[derive(Debug)]
+struct Event {
+  name: &'static str,
+  timestamp: u64,
+}
+
+#[assay]
+fn events_in_order() {
+  let events = vec![
+    Event {
+      name: "connect",
+      timestamp: 10,
+    },
+    Event {
+      name: "send",
+      timestamp: 10,
+    },
+    Event {
+      name: "close",
+      timestamp: 25,
+    },
+  ];
+  assert_ordered!(&events, |e| e.timestamp);
+  // Events at the same time are ordered by name
+  assert_ordered!(&events, |e| (e.timestamp, e.name));
+  assert_ordered!(Vec::<Event>::new(), |e| e.timestamp);
+  assert_ordered!([1.0, 2.5, 2.5, 3.0], |n| *n);
+}
+
+#[assay(ignore)]
+fn events_inverted() {
+  let events = [
+    Event {
+      name: "connect",
+      timestamp: 10,
+    },
+    Event {
+      name: "close",
+      timestamp: 25,
+    },
+    Event {
+      name: "send",
+      timestamp: 20,
+    },
+  ];
+  assert_ordered!(&events, |e| e.timestamp);
+}
+
+#[test]
+fn events_inverted_output() {
+  let output = run_ignored("events_inverted");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- events_inverted stdout ----",
+    "assertion failed: items are not ordered by `|e| e.timestamp`",
+    "item 1 has key 25: Event {\n    name: \"close\",",
+    "item 2 has key 20: Event {\n    name: \"send\",",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for ordering assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn strictly_monotonic() {
+  assert_increasing!([1, 2, 5, 9]);
+  assert_increasing!(vec!["a", "b", "c"]);
+  assert_increasing!(&[0.5, 1.0]);
+  assert_increasing!(Vec::<u8>::new());
+  assert_decreasing!([9, 5, 2, 1]);
+  assert_decreasing!((0..5).rev());
+}
+
+#[assay(ignore)]
+fn monotonic_plateau() {
+  assert_increasing!([1, 2, 2, 3]);
+}
+
+#[assay(ignore)]
+fn monotonic_wrong_direction() {
+  assert_decreasing!([3, 2, 4]);
+}
+
+#[assay(ignore)]
+fn monotonic_nan() {
+  assert_increasing!([1.0, f64::NAN]);
+}
+
+#[test]
+fn monotonic_failures_output() {
+  let output = run_ignored("monotonic_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- monotonic_plateau stdout ----",
+    "assertion failed: items are not strictly increasing\n\nitem 1: 2\nitem 2: 2",
+    "---- monotonic_wrong_direction stdout ----",
+    "assertion failed: items are not strictly decreasing\n\nitem 1: 2\nitem 2: 4",
+    "---- monotonic_nan stdout ----",
+    "item 0: 1.0\nitem 1: NaN",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for monotonic assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn stable_sort() {
+  let people = [
+    ("ann", 31),
+    ("bob", 25),
+    ("cat", 31),
+    ("dan", 25),
+    ("bob", 25),
+  ];
+  let mut sorted = people.to_vec();
+  sorted.sort_by_key(|(_, age)| *age);
+  assert_stable_sort!(people, sorted, |(_, age)| *age);
+  assert_stable_sort!(Vec::<u8>::new(), [], |n| *n);
+}
+
+#[assay(ignore)]
+fn unstable_sort_swapped() {
+  let people = [("ann", 31), ("bob", 25), ("cat", 31), ("dan", 25)];
+  let sorted = [("bob", 25), ("dan", 25), ("cat", 31), ("ann", 31)];
+  assert_stable_sort!(people, sorted, |(_, age)| *age);
+}
+
+#[assay(ignore)]
+fn unstable_sort_lost_item() {
+  assert_stable_sort!([3, 1, 2], [1, 2, 2], |n| *n);
+}
+
+#[test]
+fn unstable_sort_output() {
+  let output = run_ignored("unstable_sort_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- unstable_sort_swapped stdout ----",
+    "assertion failed: sort by `|(_, age)| *age` is not stable\n\noriginal items 0 and 2 both have key 31 but were swapped",
+    "item 0: (\n    \"ann\",\n    31,\n)\nitem 2: (\n    \"cat\",",
+    "---- unstable_sort_lost_item stdout ----",
+    "assertion failed: sort by `|n| *n` is not stable\n\nsorted item 2 isn't one of the original items: 2",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for stable sort assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+// Only `PartialEq` as `f64` isn't `Eq`, `Ord`, or `Hash`
+#[derive(Debug, PartialEq)]
+struct Reading {
+  sensor: &'static str,
+  value: f64,
+}
+
+#[assay]
+fn unordered_equal() {
+  let readings = vec![
+    Reading {
+      sensor: "a",
+      value: 1.5,
+    },
+    Reading {
+      sensor: "b",
+      value: 2.0,
+    },
+    Reading {
+      sensor: "a",
+      value: 1.5,
+    },
+  ];
+  let reordered = vec![
+    Reading {
+      sensor: "a",
+      value: 1.5,
+    },
+    Reading {
+      sensor: "a",
+      value: 1.5,
+    },
+    Reading {
+      sensor: "b",
+      value: 2.0,
+    },
+  ];
+  assert_eq_unordered!(readings, reordered);
+  assert_eq_unordered!(&[3, 1, 2], &[1, 2, 3]);
+  assert_eq_unordered!(Vec::<u8>::new(), []);
+
+  let same_ids = |ids: &[u8]| assert_eq_unordered!(ids, &[2, 3, 1]);
+  same_ids(&[1, 2, 3]);
+}
+
+#[assay(ignore)]
+fn unordered_counts_differ() {
+  assert_eq_unordered!([1, 1, 2], [1, 2, 2]);
+}
+
+#[assay(ignore)]
+fn unordered_items_differ() {
+  let left = [Reading {
+    sensor: "a",
+    value: 1.5,
+  }];
+  let right = [Reading {
+    sensor: "a",
+    value: 2.5,
+  }];
+  assert_eq_unordered!(left, right);
+}
+
+#[test]
+fn unordered_output() {
+  let output = run_ignored("unordered_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- unordered_counts_differ stdout ----",
+    "assertion failed: `left` and `right` don't have the same items\n\nonly in left: [\n    1,\n]\nonly in right: [\n    2,\n]",
+    "---- unordered_items_differ stdout ----",
+    "only in left: [\n    Reading {\n        sensor: \"a\",\n        value: 1.5,\n    },\n]",
+    "only in right: [\n    Reading {\n        sensor: \"a\",\n        value: 2.5,\n    },\n]",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for unordered assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
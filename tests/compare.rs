@@ -0,0 +1,327 @@
+use assay::{
+  assay, assert_debug_eq, assert_eq_ignore_whitespace, assert_files_eq, assert_ok_eq,
+  assert_path_eq, assert_some_eq, assert_unchanged, assert_with, testing::run_ignored,
+};
+use std::{collections::HashMap, fs};
+
+fn approx(actual: &f64, expected: &f64) -> bool {
+  (actual - expected).abs() < 1e-9
+}
+
+#[assay]
+fn approximately_equal() {
+  assert_with!(0.1 + 0.2, 0.3, approx);
+  assert_with!(
+    vec![3, 1, 2],
+    vec![1, 2, 3],
+    |a: &Vec<i32>, e: &Vec<i32>| {
+      let mut a = a.clone();
+      a.sort();
+      &a == e
+    }
+  );
+}
+
+#[assay(ignore)]
+fn approx_comparison_fails() {
+  assert_with!(0.1 + 0.2, 0.4, approx);
+}
+
+#[test]
+fn approx_comparison_fails_output() {
+  let output = run_ignored("approx_comparison_fails");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- approx_comparison_fails stdout ----",
+    "assertion failed: `approx(actual, expected)`",
+    "  actual: 0.30000000000000004",
+    "expected: 0.4",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for custom comparisons.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn equivalent_paths() {
+  use std::path::{Path, PathBuf};
+
+  assert_path_eq!("a/b/../c", "a/c");
+  assert_path_eq!("./a/./b/", "a/b");
+  assert_path_eq!(r"a\b\c", "a/b/c");
+  assert_path_eq!(PathBuf::from("/tmp/x/.."), Path::new("/tmp"));
+  assert_path_eq!("/..", "/");
+  assert_path_eq!("../a/../../b", "../../b");
+  assert_path_eq!("a/..", ".");
+}
+
+#[assay(ignore)]
+fn paths_differ() {
+  assert_path_eq!("a/./b/../c", "a/b/c");
+}
+
+#[test]
+fn paths_differ_output() {
+  let output = run_ignored("paths_differ");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- paths_differ stdout ----",
+    "assertion failed: `(left == right)` after normalizing paths",
+    "  left: a/c",
+    " right: a/b/c",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for path assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[derive(Debug)]
+struct Inventory {
+  counts: HashMap<&'static str, u32>,
+}
+
+fn inventory(items: &[(&'static str, u32)]) -> Inventory {
+  Inventory {
+    counts: items.iter().copied().collect(),
+  }
+}
+
+#[assay]
+fn debug_output_equal() {
+  let items = [("apple", 3), ("banana", 1), ("cherry", 7), ("date", 2)];
+  let mut reversed = items;
+  reversed.reverse();
+  let (left, right) = (inventory(&items), inventory(&reversed));
+  assert_debug_eq!(left, right);
+  assert_eq!(left.counts, right.counts);
+  assert_debug_eq!(vec![left], [right]);
+  assert_debug_eq!(Some(1), Some(1u8));
+}
+
+#[assay(ignore)]
+fn debug_output_differs() {
+  assert_debug_eq!(
+    inventory(&[("apple", 3), ("banana", 1)]),
+    inventory(&[("banana", 1), ("apple", 4)])
+  );
+}
+
+#[test]
+fn debug_output_differs_output() {
+  let output = run_ignored("debug_output_differs");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- debug_output_differs stdout ----",
+    "assertion failed: `(left == right)` comparing `Debug` output",
+    "apple",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for `Debug` assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn files_equal() {
+  use std::path::{Path, PathBuf};
+
+  fs::write("a.bin", [0, 1, 2, 255])?;
+  fs::copy("a.bin", "b.bin")?;
+  assert_files_eq!("a.bin", "b.bin");
+  assert_files_eq!(Path::new("a.bin"), PathBuf::from("./b.bin"));
+}
+
+#[assay(ignore)]
+fn files_differ_in_content() {
+  fs::write("a.bin", [0, 1, 2, 3])?;
+  fs::write("b.bin", [0, 1, 9, 3])?;
+  assert_files_eq!("a.bin", "b.bin");
+}
+
+#[assay(ignore)]
+fn files_differ_in_length() {
+  fs::write("short.bin", [0, 1])?;
+  fs::write("long.bin", [0, 1, 2])?;
+  assert_files_eq!("short.bin", "long.bin");
+}
+
+#[test]
+fn files_differ_output() {
+  let output = run_ignored("files_differ_in_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- files_differ_in_content stdout ----",
+    "assertion failed: `a.bin` and `b.bin` differ starting at byte 2\n\n  left: 4 bytes\n right: 4 bytes",
+    "---- files_differ_in_length stdout ----",
+    "assertion failed: `short.bin` and `long.bin` differ starting at byte 2\n\n  left: 2 bytes\n right: 3 bytes",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for file assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+// Rewrites each line with its keys sorted, which leaves sorted input alone
+fn sort_keys(path: &str) -> assay::Result<()> {
+  let sorted = fs::read_to_string(path)?
+    .lines()
+    .map(|line| {
+      let mut keys = line.split(',').collect::<Vec<_>>();
+      keys.sort();
+      keys.join(",") + "\n"
+    })
+    .collect::<String>();
+  fs::write(path, sorted)?;
+  Ok(())
+}
+
+#[assay]
+fn transform_leaves_file_unchanged() {
+  fs::write("keys.csv", "a,b,c\nx,y\n")?;
+  assert_unchanged!("keys.csv", || sort_keys("keys.csv"))?;
+  let len = assert_unchanged!(std::path::Path::new("keys.csv"), || fs::read("keys.csv")
+    .unwrap()
+    .len());
+  assert_eq!(len, 10);
+}
+
+#[assay(ignore)]
+fn unchanged_file_was_rewritten() {
+  fs::write("keys.csv", "a,b,c\ny,x\n")?;
+  assert_unchanged!("keys.csv", || sort_keys("keys.csv"))?;
+}
+
+#[assay(ignore)]
+fn unchanged_file_was_removed() {
+  fs::write("keys.csv", "a,b\n")?;
+  assert_unchanged!("keys.csv", || fs::remove_file("keys.csv"))?;
+}
+
+#[assay(ignore)]
+fn unchanged_file_was_rewritten_binary() {
+  fs::write("data.bin", [0, 159, 146, 150])?;
+  assert_unchanged!("data.bin", || fs::write("data.bin", [0, 159, 146]))?;
+}
+
+#[test]
+fn unchanged_file_output() {
+  let output = run_ignored("unchanged_file_was_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- unchanged_file_was_rewritten stdout ----",
+    "assertion failed: `keys.csv` was changed\n\n",
+    " a,b,c\n",
+    "---- unchanged_file_was_removed stdout ----",
+    "assertion failed: `keys.csv` was removed",
+    "---- unchanged_file_was_rewritten_binary stdout ----",
+    "assertion failed: `data.bin` was changed\n\nbefore: 4 bytes\n after: 3 bytes",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for unchanged assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+struct BigError {
+  code: u32,
+  context: Vec<&'static str>,
+}
+
+#[assay]
+fn ok_and_some_values_match() {
+  let result: Result<u32, BigError> = Ok(5);
+  assert_ok_eq!(result, 5);
+  assert_ok_eq!(result, 5,);
+  assert_some_eq!(Some("five"), "five");
+  assert_some_eq!("a,b".split(',').next(), "a");
+}
+
+#[assay(ignore)]
+fn ok_eq_wrong_inner() {
+  let result: Result<u32, BigError> = Ok(4);
+  assert_ok_eq!(result, 5);
+}
+
+#[assay(ignore)]
+fn ok_eq_wrong_variant() {
+  let result: Result<u32, BigError> = Err(BigError {
+    code: 7,
+    context: vec!["reading config"],
+  });
+  assert_ok_eq!(result, 5);
+}
+
+#[assay(ignore)]
+fn some_eq_wrong_variant() {
+  assert_some_eq!(None::<u32>, 5);
+}
+
+#[test]
+fn eq_wrong_output() {
+  let output = run_ignored("_eq_wrong_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- ok_eq_wrong_inner stdout ----",
+    "assertion failed: `(left == right)`: `Ok` value didn't match",
+    "---- ok_eq_wrong_variant stdout ----",
+    "assertion failed: expected `Ok`, got `Err`",
+    "   error: BigError { code: 7, context: [\"reading config\"] }",
+    "expected: Ok(5)",
+    "---- some_eq_wrong_variant stdout ----",
+    "assertion failed: expected `Some`, got `None`",
+    "expected: Some(5)",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for variant assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn only_whitespace_differs() {
+  assert_eq_ignore_whitespace!(
+    "fn main() {\n    println!(\"hi\");\n}\n",
+    "fn   main() {\n\tprintln!(\"hi\");  \n\n}"
+  );
+  assert_eq_ignore_whitespace!(String::from("  a  b  "), "a b");
+  assert_eq_ignore_whitespace!("", "\n \t\n");
+}
+
+#[assay(ignore)]
+fn text_differs_beyond_whitespace() {
+  assert_eq_ignore_whitespace!("let x = 1;\n  let y = 2;", "let x = 1;\nlet  y = 3;");
+}
+
+#[test]
+fn text_differs_beyond_whitespace_output() {
+  let output = run_ignored("text_differs_beyond_whitespace");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- text_differs_beyond_whitespace stdout ----",
+    "assertion failed: `(actual == expected)` ignoring whitespace",
+    " let x = 1;",
+    "<let y = ",
+    ">let y = ",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for whitespace comparisons.\n\nOutput:\n{tests}");
+    }
+  }
+}
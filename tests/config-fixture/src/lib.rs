@@ -0,0 +1,29 @@
+//! Tests for `assay.toml` defaults, run by `tests/config.rs` in the main crate
+
+#[cfg(test)]
+mod tests {
+  use assay::{assay, clock};
+  use std::{env, fs, path::Path, thread};
+
+  #[assay]
+  fn config_defaults() {
+    assert_eq!(fs::read_to_string("data.txt")?, "included by assay.toml\n");
+    assert_eq!(env::var("ASSAY_CONFIG")?, "from config");
+    assert!(clock().is_mocked());
+    assert_eq!(thread::current().name(), Some("from-config"));
+  }
+
+  #[assay(
+    include = [("data.txt", "renamed.txt")],
+    env = [("ASSAY_CONFIG", "from test")],
+    thread_name = "from-test",
+  )]
+  fn config_overridden() {
+    assert!(!Path::new("data.txt").exists());
+    assert!(Path::new("renamed.txt").exists());
+    assert_eq!(env::var("ASSAY_CONFIG")?, "from test");
+    assert!(env::var("ASSAY_CONFIG_OTHER").is_err());
+    assert!(clock().is_mocked());
+    assert_eq!(thread::current().name(), Some("from-test"));
+  }
+}
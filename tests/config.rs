@@ -0,0 +1,28 @@
+use assay::testing::cargo_test;
+
+#[test]
+fn assay_toml_defaults() {
+  let output = cargo_test()
+    .args([
+      "--manifest-path",
+      "tests/config-fixture/Cargo.toml",
+      "--target-dir",
+      "target/config-fixture",
+    ])
+    .output()
+    .unwrap();
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "test tests::config_defaults ... ok",
+    "test tests::config_overridden ... ok",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!(
+        "Unexpected output for assay.toml defaults.\n\nOutput:\n{tests}\n{}",
+        String::from_utf8_lossy(&output.stderr)
+      );
+    }
+  }
+}
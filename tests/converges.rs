@@ -0,0 +1,52 @@
+use assay::{assay, assert_converges, testing::run_ignored};
+
+// One step of Newton's method for the square root of `n`
+fn newton_sqrt(n: f64) -> impl FnMut() -> f64 {
+  let mut x = n;
+  move || {
+    x = (x + n / x) / 2.0;
+    x
+  }
+}
+
+#[assay]
+fn newton_converges() {
+  let root = assert_converges!(50, 1e-12, newton_sqrt(2.0));
+  assert!((root - 2f64.sqrt()).abs() < 1e-9);
+
+  let mut halved = 1024u32;
+  let settled = assert_converges!(20, 1, || {
+    halved /= 2;
+    halved
+  });
+  assert_eq!(settled, 0);
+}
+
+#[assay(ignore)]
+fn sequence_diverges() {
+  let mut x = 1.0f64;
+  assert_converges!(10, 1e-6, || {
+    x *= 2.0;
+    x
+  });
+}
+
+#[test]
+fn sequence_diverges_output() {
+  let output = run_ignored("sequence_diverges");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- sequence_diverges stdout ----",
+    "assertion failed: did not converge within 10 iterations",
+    "tolerance: 1e-6",
+    "iteration 6: 64.0\niteration 7: 128.0",
+    "iteration 10: 1024.0",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for convergence assertions.\n\nOutput:\n{tests}");
+    }
+  }
+  assert!(!tests.contains("iteration 5: "));
+}
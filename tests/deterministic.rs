@@ -0,0 +1,47 @@
+use assay::{assay, assert_deterministic, testing::run_ignored};
+use std::collections::{BTreeMap, HashMap};
+
+fn word_counts(text: &str) -> BTreeMap<&str, usize> {
+  let mut counts = BTreeMap::new();
+  for word in text.split_whitespace() {
+    *counts.entry(word).or_default() += 1;
+  }
+  counts
+}
+
+#[assay]
+fn same_output_every_run() {
+  let counts = assert_deterministic!(10, || word_counts("a b a c b a"));
+  assert_eq!(counts["a"], 3);
+  assert_eq!(assert_deterministic!(2, || 1 + 1), 2);
+}
+
+#[assay(ignore)]
+fn hash_map_order_varies() {
+  // Every `HashMap` is seeded differently so the order keys come out in
+  // changes from one map to the next
+  assert_deterministic!(10, || {
+    (0..32)
+      .map(|n| (n, n))
+      .collect::<HashMap<u32, u32>>()
+      .into_keys()
+      .collect::<Vec<_>>()
+  });
+}
+
+#[test]
+fn hash_map_order_varies_output() {
+  let output = run_ignored("hash_map_order_varies");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- hash_map_order_varies stdout ----",
+    "runs returned a different value than the first",
+    "run 1: [",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for determinism assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,96 @@
+use assay::{assay, assert_env_unchanged, testing::run_ignored};
+use std::env;
+
+#[assay(env = [("ASSAY_SET_BY_ATTRIBUTE", "1")])]
+fn env_left_alone() {
+  assert_eq!(env::var("ASSAY_SET_BY_ATTRIBUTE")?, "1");
+  env::set_var("ASSAY_TEMPORARY", "1");
+  env::remove_var("ASSAY_TEMPORARY");
+  assert_env_unchanged!();
+}
+
+#[assay]
+async fn env_left_alone_async() {
+  assert_env_unchanged!();
+}
+
+fn env_restored() {
+  assay::assert_env_unchanged!();
+}
+
+// The environment is only recorded for tests that use the assertion in their
+// own body
+#[assay(should_panic = "can only be used in the body of an `#[assay]` test")]
+fn env_unchanged_outside_body() {
+  env_restored();
+}
+
+#[assay(ignore, env = [("ASSAY_REMOVED", "gone"), ("ASSAY_CHANGED", "old")])]
+fn env_leaked_by_body() {
+  env::set_var("ASSAY_ADDED", "new");
+  env::remove_var("ASSAY_REMOVED");
+  env::set_var("ASSAY_CHANGED", "new");
+  assert_env_unchanged!();
+}
+
+#[test]
+fn env_leaked_by_body_output() {
+  let output = run_ignored("env_leaked_by_body");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- env_leaked_by_body stdout ----",
+    "assertion failed: environment changed since the test started\n\n\
+     + ASSAY_ADDED=\"new\"\n\
+     ~ ASSAY_CHANGED: \"old\" -> \"new\"\n\
+     - ASSAY_REMOVED=\"gone\"",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for env assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(env_file = "tests/fixtures/test.env", env = [("ASSAY_OVERRIDDEN", "from env")])]
+fn env_from_file() {
+  assert_eq!(env::var("ASSAY_DATABASE")?, "postgres://localhost/test");
+  assert_eq!(env::var("ASSAY_QUOTED")?, "two words");
+  assert_eq!(env::var("ASSAY_SINGLE")?, "single");
+  assert_eq!(env::var("ASSAY_OVERRIDDEN")?, "from env");
+  assert!(env::var_os("# Loaded by the env_file tests").is_none());
+}
+
+#[assay(ignore, env_file = "tests/fixtures/missing.env")]
+fn env_file_missing() {}
+
+#[assay(ignore, env_file = "Cargo.toml")]
+fn env_file_malformed() {}
+
+#[test]
+fn env_file_problems_output() {
+  let output = run_ignored("env_file_m");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- env_file_missing stdout ----",
+    "Error: cannot read env file `",
+    "tests/fixtures/missing.env`",
+    "---- env_file_malformed stdout ----",
+    "Cargo.toml` line 1: expected `KEY=value`",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for env files.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(
+  env_remove = ["HOME", "ASSAY_SET_THEN_REMOVED"],
+  env = [("ASSAY_SET_THEN_REMOVED", "set again")],
+)]
+fn env_removed() {
+  assert!(env::var_os("HOME").is_none());
+  assert_eq!(env::var("ASSAY_SET_THEN_REMOVED")?, "set again");
+}
@@ -0,0 +1,127 @@
+use assay::{assay, eyre::ensure, testing::run_ignored, TestFixture};
+use std::{fs, path::PathBuf};
+
+struct Workspace {
+  root: PathBuf,
+  files: Vec<String>,
+}
+
+impl TestFixture for Workspace {
+  fn setup() -> assay::Result<Self> {
+    let root = PathBuf::from("workspace");
+    fs::create_dir(&root)?;
+    Ok(Self {
+      root,
+      files: Vec::new(),
+    })
+  }
+
+  fn teardown(self) -> assay::Result<()> {
+    println!("tearing down {} files", self.files.len());
+    for file in &self.files {
+      assert!(self.root.join(file).is_file());
+    }
+    fs::remove_dir_all(&self.root)?;
+    Ok(())
+  }
+}
+
+impl Workspace {
+  fn create(&mut self, name: &str) -> assay::Result<()> {
+    fs::write(self.root.join(name), name)?;
+    self.files.push(name.to_string());
+    Ok(())
+  }
+}
+
+#[assay(fixture = Workspace)]
+fn fixture_is_available() {
+  assert!(fixture.root.is_dir());
+  fixture.create("a.txt")?;
+  fixture.create("b.txt")?;
+  assert_eq!(fixture.files, ["a.txt", "b.txt"]);
+}
+
+#[assay(fixture = Workspace)]
+async fn fixture_is_available_async() {
+  fixture.create("a.txt")?;
+  assert_eq!(fs::read_to_string("workspace/a.txt")?, "a.txt");
+}
+
+#[assay(fixture = Workspace, ignore)]
+fn fixture_torn_down_after_panic() {
+  fixture.create("a.txt")?;
+  panic!("body failed");
+}
+
+#[test]
+fn fixture_torn_down_after_panic_output() {
+  let output = run_ignored("fixture_torn_down_after_panic");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- fixture_torn_down_after_panic stdout ----",
+    "body failed",
+    "tearing down 1 files",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for fixtures.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn fixture_as_parameter(workspace: &mut Workspace) {
+  workspace.create("a.txt")?;
+  assert_eq!(workspace.files, ["a.txt"]);
+}
+
+#[assay]
+async fn fixture_as_parameter_async(workspace: &mut Workspace) {
+  workspace.create("a.txt")?;
+  assert_eq!(fs::read_to_string("workspace/a.txt")?, "a.txt");
+}
+
+struct Counter {
+  count: u32,
+}
+
+impl TestFixture for Counter {
+  fn setup() -> assay::Result<Self> {
+    Ok(Self { count: 0 })
+  }
+
+  fn teardown(self) -> assay::Result<()> {
+    ensure!(self.count == 3, "teardown saw a count of {}", self.count);
+    Ok(())
+  }
+}
+
+#[assay]
+fn parameter_changes_reach_teardown(counter: &mut Counter) {
+  for _ in 0..3 {
+    counter.count += 1;
+  }
+}
+
+#[assay(ignore)]
+fn parameter_counted_once(counter: &mut Counter) {
+  counter.count += 1;
+}
+
+#[test]
+fn parameter_counted_once_output() {
+  let output = run_ignored("parameter_counted_once");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- parameter_counted_once stdout ----",
+    "Error: teardown saw a count of 1",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for fixture parameters.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,61 @@
+use assay::{assay, testing::cargo_test};
+
+fn cli_args(verbose: bool, quiet: bool) -> Vec<&'static str> {
+  let mut args = Vec::new();
+  if verbose {
+    args.push("--verbose");
+  }
+  if quiet {
+    args.push("--quiet");
+  }
+  args
+}
+
+#[assay(flags = [verbose, quiet])]
+fn cli() {
+  let args = cli_args(verbose, quiet);
+  assert_eq!(args.contains(&"--verbose"), verbose);
+  assert_eq!(args.contains(&"--quiet"), quiet);
+  assert_eq!(args.len(), verbose as usize + quiet as usize);
+}
+
+#[assay(flags = [color], env = [("TERM", "xterm")])]
+async fn flags_async() {
+  assert_eq!(std::env::var("TERM")?, "xterm");
+  let _: bool = color;
+}
+
+#[assay(flags = [dry_run, force], name_separator = "__")]
+fn deploy() {
+  assert_eq!(
+    cli_args(dry_run, force).len(),
+    dry_run as usize + force as usize
+  );
+}
+
+#[test]
+fn flag_test_names() {
+  let output = cargo_test()
+    .args(["--test", "flags", "--", "--list"])
+    .output()
+    .unwrap();
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "cli_none: test",
+    "cli_verbose: test",
+    "cli_quiet: test",
+    "cli_verbose_quiet: test",
+    "flags_async_none: test",
+    "flags_async_color: test",
+    "deploy__none: test",
+    "deploy__dry_run: test",
+    "deploy__force: test",
+    "deploy__dry_run__force: test",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected tests generated for flags.\n\nOutput:\n{tests}");
+    }
+  }
+}
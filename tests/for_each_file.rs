@@ -0,0 +1,40 @@
+use assay::assay;
+use std::fs;
+
+#[assay(for_each_file = "tests/cases/*.input")]
+fn uppercase() {
+  let output = fs::read_to_string(&input)?.to_uppercase();
+  assert_eq!(output, fs::read_to_string(&expected)?);
+  assert!(input.is_absolute());
+  assert_eq!(input.extension(), Some("input".as_ref()));
+}
+
+#[assay(for_each_file = "tests/cases/h?ll*o.input")]
+fn wildcards() {
+  assert!(input
+    .file_name()
+    .unwrap()
+    .to_string_lossy()
+    .starts_with("hello."));
+}
+
+#[assay(for_each_file = "tests/cases/*.input", flags = [trim])]
+fn with_flags() {
+  let text = fs::read_to_string(&input)?;
+  let text = if trim { text.trim() } else { &text };
+  assert!(!text.is_empty());
+}
+
+#[test]
+fn one_test_per_file() {
+  // Each of these only exists if a test was generated for that file
+  let tests: [fn() -> assay::Result<()>; 6] = [
+    uppercase_hello,
+    uppercase_two_words,
+    wildcards_hello,
+    with_flags_hello_none,
+    with_flags_two_words_trim,
+    with_flags_hello_trim,
+  ];
+  assert_eq!(tests.len(), 6);
+}
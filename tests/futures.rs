@@ -0,0 +1,48 @@
+use assay::{assay, assert_ready, testing::run_ignored};
+use std::{
+  future::{self, Future},
+  pin::Pin,
+  task::{Context, Poll},
+};
+
+struct ReadyOnPoll;
+impl Future for ReadyOnPoll {
+  type Output = u8;
+  fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+    Poll::Ready(7)
+  }
+}
+
+#[assay]
+fn ready_on_first_poll() {
+  assert_eq!(assert_ready!(ReadyOnPoll), 7);
+  assert_eq!(assert_ready!(async { 1 + 1 }), 2);
+  let mut unpin = future::ready("done");
+  assert_eq!(assert_ready!(&mut unpin), "done");
+}
+
+#[assay]
+async fn ready_in_async_test() {
+  assert_eq!(assert_ready!(ReadyOnPoll), ReadyOnPoll.await);
+}
+
+#[assay(ignore)]
+fn still_pending_when_polled() {
+  assert_ready!(future::pending::<()>());
+}
+
+#[test]
+fn still_pending_when_polled_output() {
+  let output = run_ignored("still_pending_when_polled");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- still_pending_when_polled stdout ----",
+    "assertion failed: `future::pending::<()>()` was still pending when polled",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for ready assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,70 @@
+#![cfg(unix)]
+
+use assay::{assay, assert_command_golden, sandbox_dir, testing::run_ignored};
+use std::{fs, process::Command};
+
+fn script(script: &str) -> Command {
+  let mut cmd = Command::new("sh");
+  cmd.args(["-c", script]);
+  cmd
+}
+
+#[assay]
+fn golden_output_matches() {
+  assert_command_golden!(
+    script("echo out; echo err >&2; pwd"),
+    "tests/golden/interleaved.txt"
+  );
+}
+
+#[assay(env = [("ASSAY_BLESS", "1")])]
+fn golden_output_blessed() {
+  let golden = sandbox_dir().unwrap().join("new/golden.txt");
+  assert_command_golden!(script("echo blessed; pwd >&2"), &golden);
+  assert_eq!(fs::read_to_string(golden)?, "blessed\n<TMPDIR>\n");
+}
+
+#[assay(ignore)]
+fn golden_mismatched_output() {
+  assert_command_golden!(script("echo actual"), "tests/golden/mismatch.txt");
+}
+
+#[assay(ignore)]
+fn golden_missing_file() {
+  assert_command_golden!(script("true"), "tests/golden/missing.txt");
+}
+
+#[assay(stdout_golden = "tests/golden/stdout.txt")]
+fn stdout_matches_golden() {
+  println!("report for {}", std::env::current_dir()?.display());
+  println!("generated at 2024-05-01T12:00:00Z");
+  print!("no trailing newline");
+}
+
+#[assay(ignore, stdout_golden = "tests/golden/mismatch.txt")]
+fn golden_mismatched_stdout() {
+  println!("actual");
+}
+
+#[test]
+fn golden_failures_output() {
+  let output = run_ignored("golden_m");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- golden_mismatched_output stdout ----",
+    "assertion failed: output of `sh` doesn't match `",
+    "tests/golden/mismatch.txt`",
+    "run with `ASSAY_BLESS=1` to update it",
+    "---- golden_mismatched_stdout stdout ----",
+    "assertion failed: stdout of the test doesn't match `",
+    "---- golden_missing_file stdout ----",
+    "assertion failed: cannot read golden file `",
+    "run with `ASSAY_BLESS=1` to create it",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for golden files.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,72 @@
+use assay::assay;
+use std::collections::BTreeMap;
+
+trait Store {
+  fn new() -> Self;
+  fn set(&mut self, key: &str, value: u32);
+  fn get(&self, key: &str) -> Option<u32>;
+}
+
+struct VecStore(Vec<(String, u32)>);
+
+impl Store for VecStore {
+  fn new() -> Self {
+    Self(Vec::new())
+  }
+  fn set(&mut self, key: &str, value: u32) {
+    self.0.retain(|(k, _)| k != key);
+    self.0.push((key.to_string(), value));
+  }
+  fn get(&self, key: &str) -> Option<u32> {
+    self.0.iter().find(|(k, _)| k == key).map(|(_, v)| *v)
+  }
+}
+
+struct BTreeStore(BTreeMap<String, u32>);
+
+impl Store for BTreeStore {
+  fn new() -> Self {
+    Self(BTreeMap::new())
+  }
+  fn set(&mut self, key: &str, value: u32) {
+    self.0.insert(key.to_string(), value);
+  }
+  fn get(&self, key: &str) -> Option<u32> {
+    self.0.get(key).copied()
+  }
+}
+
+mod generic {
+  pub struct Wrapper<T>(pub T);
+}
+
+#[assay(impls = [VecStore, BTreeStore])]
+fn store_contract() {
+  let mut store = Sut::new();
+  assert_eq!(store.get("a"), None);
+  store.set("a", 1);
+  store.set("b", 2);
+  store.set("a", 3);
+  assert_eq!(store.get("a"), Some(3));
+  assert_eq!(store.get("b"), Some(2));
+}
+
+#[assay(impls = [generic::Wrapper<u8>, Vec<&'static str>])]
+fn impls_with_paths_and_generics() {
+  let name = std::any::type_name::<Sut>();
+  assert!(
+    name.ends_with("Wrapper<u8>") || name.ends_with("Vec<&str>"),
+    "{name}"
+  );
+}
+
+#[test]
+fn impls_named_after_types() {
+  // Only compiles if each type got a test with the expected name
+  let _ = [
+    store_contract_vec_store,
+    store_contract_b_tree_store,
+    impls_with_paths_and_generics_wrapper,
+    impls_with_paths_and_generics_vec,
+  ];
+}
@@ -6,7 +6,11 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use assay::{assay, assert_eq_sorted};
+// `#[assay]` already brings `assert_eq_sorted!` into the test body, so this
+// import only shows where it comes from
+#[allow(unused_imports)]
+use assay::assert_eq_sorted;
+use assay::{assay, assert_in_sandbox, assert_send, assert_sync, assert_within_dir};
 use std::{
   collections::HashMap,
   env, fs,
@@ -37,6 +41,181 @@ fn include() {
   assert!(fs::metadata("Cargo.toml")?.is_file());
 }
 
+#[assay(include = [("Cargo.toml", "config/manifest.toml"), ("src/lib.rs", "config/")])]
+fn include_as() {
+  assert!(fs::metadata("config/manifest.toml")?.is_file());
+  assert!(fs::metadata("config/lib.rs")?.is_file());
+  assert!(!PathBuf::from("Cargo.toml").exists());
+  assert!(!PathBuf::from("config/src").exists());
+}
+
+#[assay(include = ["Cargo.toml", ("Cargo.toml", "nested/dir/")])]
+fn include_into_nested_dir() {
+  assert_eq!(
+    fs::read_to_string("Cargo.toml")?,
+    fs::read_to_string("nested/dir/Cargo.toml")?
+  );
+}
+
+#[assay(include = [("Cargo.toml", "manifest.json"), ("README.md", "docs/readme")])]
+fn include_as_other_extension() {
+  assert!(PathBuf::from("manifest.json").is_file());
+  assert!(PathBuf::from("docs/readme").is_file());
+  assert!(!PathBuf::from("manifest.toml").exists());
+  assert!(!PathBuf::from("docs/readme.md").exists());
+}
+
+#[assay(
+  include = ["README.md"],
+  include_as_ext = [("tests/fixtures/data", "json"), ("Cargo.toml", ".json")],
+)]
+fn include_as_ext() {
+  let data = PathBuf::from("tests/fixtures/data.json");
+  assert_eq!(data.extension(), Some("json".as_ref()));
+  assert_eq!(fs::read_to_string(data)?, "{\"name\": \"assay\"}\n");
+  assert!(!PathBuf::from("tests/fixtures/data").exists());
+  assert!(PathBuf::from("Cargo.json").is_file());
+  assert!(!PathBuf::from("Cargo.toml").exists());
+  assert!(PathBuf::from("README.md").is_file());
+}
+
+#[assay(link = ["Cargo.toml", ("src/lib.rs", "linked/lib.rs")])]
+fn link() {
+  assert_eq!(
+    fs::read_to_string("Cargo.toml")?,
+    include_str!("../Cargo.toml")
+  );
+  assert_eq!(
+    fs::read_to_string("linked/lib.rs")?,
+    include_str!("../src/lib.rs")
+  );
+  #[cfg(unix)]
+  {
+    let sandbox = assay::sandbox_dir().unwrap();
+    let target = fs::read_link(sandbox.join("linked/lib.rs"))?;
+    assert!(target.is_absolute());
+    assert!(fs::symlink_metadata("Cargo.toml")?.file_type().is_symlink());
+  }
+}
+
+#[assay(include = ["README.md"], link = [("Cargo.toml", "config/")])]
+fn link_alongside_include() {
+  assert!(PathBuf::from("README.md").exists());
+  assert!(PathBuf::from("config/Cargo.toml").exists());
+}
+
+#[assay(include = ["Cargo.toml"], include_dir = ["tests/fixtures/tree", ("tests/fixtures/tree/nested", "copy")])]
+fn include_dir() {
+  assert!(PathBuf::from("Cargo.toml").exists());
+  assert_eq!(fs::read_to_string("tests/fixtures/tree/top.txt")?, "top\n");
+  assert_eq!(
+    fs::read_to_string("tests/fixtures/tree/nested/inner.txt")?,
+    "inner\n"
+  );
+  assert_eq!(
+    fs::read_to_string("tests/fixtures/tree/nested/deeper/deepest.txt")?,
+    "deepest\n"
+  );
+  assert_eq!(fs::read_to_string("copy/deeper/deepest.txt")?, "deepest\n");
+  assert!(!PathBuf::from("copy/top.txt").exists());
+}
+
+#[assay(
+  include = [
+    "tests/fixtures/tree/*.txt",
+    ("tests/fixtures/tree/nested/*", "globbed/"),
+    ("tests/fixtures/tree/t?p.txt", "renamed/*.txt", ro),
+  ]
+)]
+fn include_glob() {
+  // Without a destination the matches go at the root
+  assert_eq!(fs::read_to_string("top.txt")?, "top\n");
+  assert_eq!(fs::read_to_string("other.txt")?, "other\n");
+  assert!(!PathBuf::from("tests").exists());
+  assert_eq!(fs::read_to_string("globbed/inner.txt")?, "inner\n");
+  // Only files match so the directory next to it isn't included
+  assert!(!PathBuf::from("globbed/deeper").exists());
+  assert!(fs::metadata("renamed/top.txt")?.permissions().readonly());
+}
+
+#[assay(
+  include = ["Cargo.toml"],
+  create = [
+    ("config.toml", "key = 'value'"),
+    ("nested/dir/empty", ""),
+    ("Cargo.toml", "[package]"),
+  ]
+)]
+fn create_files() {
+  assert_eq!(fs::read_to_string("config.toml")?, "key = 'value'");
+  assert_eq!(fs::read_to_string("nested/dir/empty")?, "");
+  // Created files are written after included ones
+  assert_eq!(fs::read_to_string("Cargo.toml")?, "[package]");
+}
+
+#[assay]
+fn create_file_stays_in_temp_dir() {
+  let fs = assay::PrivateFS::new()?;
+  for path in ["/escaped.txt", "../escaped.txt", "nested/../../escaped.txt"] {
+    let error = fs.create_file(path, "").unwrap_err().to_string();
+    assert!(error.contains("must be relative"), "{error}");
+  }
+  assert!(!PathBuf::from("../escaped.txt").exists());
+}
+
+#[assay]
+fn include_dir_at_runtime() {
+  let fs = assay::PrivateFS::new()?;
+  fs.include_dir(
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tree"),
+    "fixtures",
+  )?;
+  assert_eq!(fs::read_to_string("fixtures/nested/inner.txt")?, "inner\n");
+}
+
+#[cfg(unix)]
+#[assay]
+fn include_dir_keeps_links() {
+  fs::create_dir_all("tree/nested")?;
+  fs::write("tree/nested/inner.txt", "inner")?;
+  std::os::unix::fs::symlink("nested/inner.txt", "tree/file_link")?;
+  // Following this would copy `tree` into itself forever
+  std::os::unix::fs::symlink("..", "tree/nested/up")?;
+
+  let fs = assay::PrivateFS::new()?;
+  fs.include_dir("tree", "copied")?;
+  assert_eq!(
+    fs::read_link("copied/file_link")?,
+    PathBuf::from("nested/inner.txt")
+  );
+  assert_eq!(fs::read_to_string("copied/file_link")?, "inner");
+  assert_eq!(fs::read_link("copied/nested/up")?, PathBuf::from(".."));
+}
+
+#[assay(include = [("Cargo.toml", ro), ("src/lib.rs", "lib.rs", ro), "README.md"])]
+fn include_readonly() {
+  assert!(fs::metadata("Cargo.toml")?.permissions().readonly());
+  assert!(fs::metadata("lib.rs")?.permissions().readonly());
+  assert!(!fs::metadata("README.md")?.permissions().readonly());
+}
+
+#[assay(include = ["Cargo.toml", ("src/lib.rs", "lib.rs")], include_readonly)]
+fn include_readonly_everything() {
+  assert!(fs::metadata("Cargo.toml")?.permissions().readonly());
+  assert!(fs::metadata("lib.rs")?.permissions().readonly());
+}
+
+#[assay(include = ["Cargo.toml", ("README.md", "Cargo.toml")])]
+fn include_overwrites_by_default() {
+  assert!(fs::read_to_string("Cargo.toml")?.starts_with("# assay"));
+}
+
+#[assay(include = ["Cargo.toml", "README.md"], include_no_overwrite)]
+fn include_no_overwrite_without_collision() {
+  assert!(fs::metadata("Cargo.toml")?.is_file());
+  assert!(fs::metadata("README.md")?.is_file());
+}
+
 #[assay(should_panic)]
 fn hash_map_comparison() {
   let map1: HashMap<String, u8> = (0..5).map(|n| (n.to_string(), n)).collect();
@@ -46,6 +225,20 @@ fn hash_map_comparison() {
   assert_eq_sorted!(map1, map2);
 }
 
+#[assay(sorted_asserts)]
+fn sorted_asserts_hash_map_comparison() {
+  let map1: HashMap<String, u8> = (0..5).map(|n| (n.to_string(), n)).collect();
+  let map2: HashMap<String, u8> = (0..5).rev().map(|n| (n.to_string(), n)).collect();
+  assert_eq!(map1, map2);
+  assert_ne!(map1, HashMap::new());
+}
+
+#[assay(sorted_asserts, should_panic)]
+fn sorted_asserts_ne_equal_maps() {
+  let map: HashMap<String, u8> = (0..5).map(|n| (n.to_string(), n)).collect();
+  assert_ne!(map, map.clone());
+}
+
 #[assay]
 async fn async_func() {
   ReadyOnPoll.await;
@@ -66,6 +259,110 @@ fn multiple_attribute_values_in_different_order() {
   panic!("Panic! At The Proc-Macro 3: Attribute Switcharoo");
 }
 
+#[assay(ignore = "only runs when asked for")]
+fn ignored_with_reason() {
+  assert_in_sandbox!();
+}
+
+#[test]
+fn ignore_reason_shown() {
+  let cargo = env::var("CARGO").unwrap();
+  let run = |args: &[&str]| {
+    let output = std::process::Command::new(&cargo)
+      .args(["test", "--test", "integration_tests", "--"])
+      .args(args)
+      .output()
+      .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+  };
+
+  let skipped = run(&["--exact", "ignored_with_reason"]);
+  assert!(
+    skipped.contains("test ignored_with_reason ... ignored, only runs when asked for"),
+    "{skipped}"
+  );
+  let ran = run(&["--ignored", "--exact", "ignored_with_reason"]);
+  assert!(ran.contains("test ignored_with_reason ... ok"), "{ran}");
+}
+
+#[assay(ignore, keep_on_failure, create = [("left.txt", "behind")])]
+fn keep_failed_temp_dir() {
+  panic!("failed on purpose");
+}
+
+#[assay(ignore, create = [("left.txt", "behind")])]
+fn keep_temp_from_env() {
+  fs::read_to_string("missing.txt")?;
+}
+
+#[assay(ignore, keep_on_failure)]
+fn keep_passing_temp_dir() {
+  let dir = assay::sandbox_dir().unwrap();
+  fs::write(
+    concat!(env!("CARGO_MANIFEST_DIR"), "/target/keep-passing"),
+    dir.to_str().unwrap(),
+  )?;
+}
+
+#[test]
+fn kept_temp_dirs() {
+  let cargo = env::var("CARGO").unwrap();
+  let run = |name: &str, keep: &str| {
+    let output = std::process::Command::new(&cargo)
+      .args([
+        "test",
+        "--test",
+        "integration_tests",
+        "--",
+        "--ignored",
+        "--exact",
+        name,
+      ])
+      .env("ASSAY_KEEP_TEMP", keep)
+      .output()
+      .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+  };
+  let kept = |output: &str| {
+    output
+      .lines()
+      .find_map(|line| line.strip_prefix("assay: kept temp dir of failed test at "))
+      .map(PathBuf::from)
+  };
+
+  for (name, keep) in [("keep_failed_temp_dir", "0"), ("keep_temp_from_env", "1")] {
+    let output = run(name, keep);
+    let dir = kept(&output).unwrap_or_else(|| panic!("no kept temp dir for {name}:\n{output}"));
+    assert_eq!(fs::read_to_string(dir.join("left.txt")).unwrap(), "behind");
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  let output = run("keep_temp_from_env", "0");
+  assert_eq!(kept(&output), None, "{output}");
+
+  let output = run("keep_passing_temp_dir", "0");
+  assert!(
+    output.contains("test keep_passing_temp_dir ... ok"),
+    "{output}"
+  );
+  let dir =
+    fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/target/keep-passing")).unwrap();
+  assert!(!PathBuf::from(dir).exists());
+}
+
+#[assay(should_panic = "index out of bounds")]
+fn panic_with_expected_message() {
+  let items: Vec<u8> = Vec::new();
+  let _ = items[items.len()];
+}
+
+#[assay(include = ["Cargo.toml"], should_panic = "Cargo.toml", env = [("GOODBOY", "Bukka")])]
+fn expected_panic_with_other_attribute_values() {
+  assert_eq!(env::var("GOODBOY")?, "Bukka");
+  assert!(PathBuf::from("Cargo.toml").exists());
+  panic!("Panic! At The Proc-Macro 4: Cargo.toml Expectations");
+}
+
 #[assay(
   env = [
     ("GOODBOY", "Bukka"),
@@ -77,6 +374,28 @@ fn env_vars() {
   assert_eq!(env::var("BADDOGS")?, "false");
 }
 
+#[assay(
+  env = [
+    ("ASSAY_BASE", "/opt"),
+    ("ASSAY_BIN", "$ASSAY_BASE/bin"),
+    ("ASSAY_LIB", "${ASSAY_BASE}_lib:$ASSAY_BIN"),
+    ("ASSAY_HOME", "${HOME}"),
+    ("ASSAY_LITERAL", "$$ASSAY_BASE $ ${ASSAY_UNSET_VAR}."),
+  ],
+  env_expand,
+)]
+fn env_vars_expanded() {
+  assert_eq!(env::var("ASSAY_BIN")?, "/opt/bin");
+  assert_eq!(env::var("ASSAY_LIB")?, "/opt_lib:/opt/bin");
+  assert_eq!(env::var("ASSAY_HOME")?, env::var("HOME")?);
+  assert_eq!(env::var("ASSAY_LITERAL")?, "$ASSAY_BASE $ .");
+}
+
+#[assay(env = [("ASSAY_BASE", "/opt"), ("ASSAY_BIN", "$ASSAY_BASE/bin")])]
+fn env_vars_not_expanded_by_default() {
+  assert_eq!(env::var("ASSAY_BIN")?, "$ASSAY_BASE/bin");
+}
+
 #[assay(
   setup = setup_func(5)?,
   teardown = teardown_func(),
@@ -93,6 +412,32 @@ fn setup_teardown_test_2() {
   assert_eq!(fs::read_to_string("setup")?, "Value: 5");
 }
 
+#[assay(
+  include = ["Cargo.toml"],
+  env = [("GOODBOY", "Bukka")],
+  setup = setup_func_2(),
+  let manifest = fs::read_to_string("Cargo.toml")?,
+  let greeting = format!("{} is a {}", env::var("GOODBOY")?, fs::read_to_string("setup")?),
+  let lines = manifest.lines().count(),
+)]
+fn let_bindings() {
+  assert!(manifest.starts_with("[package]"));
+  assert_eq!(greeting, "Bukka is a Value: 5");
+  assert!(lines > 1);
+}
+
+#[assay(let data = [1, 2, 3], env = [("GOODBOY", "Bukka")])]
+async fn let_bindings_async() {
+  ReadyOnPoll.await;
+  assert_eq!(data.iter().sum::<i32>(), 6);
+  assert_eq!(env::var("GOODBOY")?, "Bukka");
+}
+
+#[assay(bench, iterations = 3, let data = [1, 2, 3])]
+fn let_bindings_bench() {
+  assert_eq!(data.len(), 3);
+}
+
 #[assay(
   setup = setup_func_2(),
   include = ["Cargo.toml", "src/lib.rs"],
@@ -139,6 +484,169 @@ async fn one_test_to_call_it_all_2() {
   panic!();
 }
 
+#[assay(config = {
+  setup: setup_func(5)?,
+  include: ["Cargo.toml", ("src/lib.rs", "lib.rs")],
+  env: [("GOODBOY", "Bukka")],
+  teardown: teardown_func(),
+})]
+fn grouped_config() {
+  assert_eq!(env::var("GOODBOY")?, "Bukka");
+  assert_eq!(fs::read_to_string("setup")?, "Value: 5");
+  assert!(PathBuf::from("Cargo.toml").exists());
+  assert!(PathBuf::from("lib.rs").exists());
+}
+
+#[assay(
+  config = { thread_name: "grouped", env: [("BADDOGS", "false")] },
+  include = ["Cargo.toml"],
+)]
+fn grouped_config_mixed_with_inline() {
+  assert_eq!(std::thread::current().name(), Some("grouped"));
+  assert_eq!(env::var("BADDOGS")?, "false");
+  assert!(PathBuf::from("Cargo.toml").exists());
+}
+
+#[assay]
+fn runs_in_split_child() {
+  assert!(assay::is_split_child());
+}
+
+// Both use 8MiB of stack, four times the 2MiB a test thread gets by default
+// and a quarter of what they ask for
+const STACK_USED: usize = 8 * 1024 * 1024;
+
+#[assay(thread_stack_size = "32MiB")]
+fn big_stack() {
+  assert!(use_stack(STACK_USED) >= STACK_USED);
+  assert_eq!(std::thread::current().name(), Some("big_stack"));
+}
+
+#[assay(thread_stack_size = "32MiB", thread_name = "deep-recursion")]
+async fn big_stack_async() {
+  ReadyOnPoll.await;
+  assert!(use_stack(STACK_USED) >= STACK_USED);
+  assert_eq!(std::thread::current().name(), Some("deep-recursion"));
+}
+
+/// Recurse until at least `bytes` of stack are in use, returning how much was.
+/// It's measured from the addresses of locals rather than counting calls so it
+/// doesn't depend on how big each frame is in a given build.
+fn use_stack(bytes: usize) -> usize {
+  fn recurse(top: usize, bytes: usize) -> usize {
+    let frame = std::hint::black_box([0u8; 1024]);
+    let used = top.abs_diff(frame.as_ptr() as usize);
+    if used >= bytes {
+      used
+    } else {
+      // Adding after the call keeps it from being turned into a loop
+      recurse(top, bytes) + frame[0] as usize
+    }
+  }
+  let top = std::hint::black_box(0u8);
+  recurse(&top as *const u8 as usize, bytes)
+}
+
+#[cfg(unix)]
+#[assay(path = ["./bin"])]
+fn custom_path() {
+  use std::{os::unix::fs::PermissionsExt, process::Command};
+
+  fs::create_dir("bin")?;
+  fs::write("bin/greet", "#!/bin/sh\necho hello\n")?;
+  fs::set_permissions("bin/greet", fs::Permissions::from_mode(0o755))?;
+
+  assert_eq!(
+    env::var("PATH")?,
+    assay::sandbox_dir()
+      .unwrap()
+      .join("./bin")
+      .display()
+      .to_string()
+  );
+  assert_eq!(Command::new("greet").output()?.stdout, b"hello\n");
+  assert!(Command::new("cargo").output().is_err());
+}
+
+#[assay]
+fn within_dir_allows_paths_inside() {
+  fs::create_dir("base")?;
+  assert_within_dir!("base", "base/new/file.txt");
+  assert_within_dir!("base", "base/../base/./file.txt");
+  assert_within_dir!(env::current_dir()?, "base");
+}
+
+#[assay]
+fn in_dir_restores_cwd() {
+  let start = env::current_dir()?;
+  let inner = assay::in_dir("nested/subdir", || {
+    fs::write("file", "in subdir").unwrap();
+    env::current_dir().unwrap()
+  })?;
+  assert_eq!(inner, start.join("nested/subdir"));
+  assay::in_dir("nested", || assert_in_sandbox!())?;
+  assert_in_sandbox!();
+  assert_eq!(env::current_dir()?, start);
+  assert_eq!(fs::read_to_string("nested/subdir/file")?, "in subdir");
+
+  let panicked = std::panic::catch_unwind(|| {
+    assay::in_dir("other", || panic!("in other")).unwrap();
+  });
+  assert!(panicked.is_err());
+  assert_eq!(env::current_dir()?, start);
+}
+
+#[assay]
+fn temp_file_with_contents() {
+  let seed = assay::temp_file_with("seed.json", r#"{"k":1}"#)?;
+  assert_eq!(seed, env::current_dir()?.join("seed.json"));
+  assert_eq!(fs::read_to_string(&seed)?, r#"{"k":1}"#);
+
+  let nested = assay::temp_file_with("data/nested/bytes", [0, 1, 2])?;
+  assert!(nested.is_absolute());
+  assert_eq!(fs::read(nested)?, [0, 1, 2]);
+
+  let replaced = assay::temp_file_with("seed.json", "replaced")?;
+  assert_eq!(fs::read_to_string(replaced)?, "replaced");
+}
+
+#[assay(args = ["--verbose", "input.txt", "two words"])]
+fn synthetic_args() {
+  let args = assay::test_args();
+  assert_eq!(args, ["--verbose", "input.txt", "two words"]);
+
+  let verbose = args.iter().any(|arg| arg == "--verbose");
+  let positional = args
+    .iter()
+    .filter(|arg| !arg.starts_with("--"))
+    .collect::<Vec<_>>();
+  assert!(verbose);
+  assert_eq!(positional, ["input.txt", "two words"]);
+}
+
+#[assay]
+fn no_synthetic_args() {
+  assert!(assay::test_args().is_empty());
+}
+
+#[assay]
+fn send_and_sync() {
+  type Shared = std::sync::Arc<std::sync::Mutex<u8>>;
+  assert_send!(Shared, String, Vec<u8>);
+  assert_sync!(Shared, str);
+}
+
+#[assay(inline)]
+fn inline_in_process() {
+  assert!(assay::sandbox_dir().is_none());
+  assert_eq!(vec![1, 2, 3], [1, 2, 3]);
+}
+
+#[assay(inline, should_panic)]
+fn inline_should_panic() {
+  panic!("inline panic");
+}
+
 fn setup_func(input: i32) -> assay::Result<()> {
   fs::write("setup", format!("Value: {}", input))?;
   Ok(())
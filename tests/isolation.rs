@@ -0,0 +1,37 @@
+use assay::{assay, testing::ignored_command};
+use std::env;
+
+#[assay(strict_isolation)]
+fn nothing_leaked() {
+  // A sandboxed test is free to change things as it has its own process
+  env::set_var("ASSAY_OWN_PROCESS", "1");
+}
+
+#[assay(ignore, inline)]
+fn leaky_first_sets_var() {
+  env::set_var("ASSAY_LEAKED", "yes");
+}
+
+#[assay(ignore, strict_isolation)]
+fn leaky_second_is_checked() {}
+
+#[test]
+fn leaked_state_detected() {
+  // One thread runs the tests in name order so the leak always comes first
+  let output = ignored_command("leaky_")
+    .arg("--test-threads=1")
+    .output()
+    .unwrap();
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "test leaky_first_sets_var ... ok",
+    "test leaky_second_is_checked ... FAILED",
+    "assertion failed: state was left behind by an earlier test in this process\n\n+ ASSAY_LEAKED=\"yes\"",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for strict isolation.\n\nOutput:\n{tests}");
+    }
+  }
+}
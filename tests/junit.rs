@@ -0,0 +1,63 @@
+use assay::{assay, testing::ignored_command};
+use std::fs;
+
+#[assay(ignore)]
+fn junit_case_passes() {}
+
+#[assay(ignore)]
+fn junit_case_fails() {
+  panic!("<broken> & \"quoted\"");
+}
+
+#[assay(ignore, should_panic)]
+fn junit_case_should_panic() {
+  panic!("expected");
+}
+
+#[test]
+fn junit_report() {
+  let report = "target/junit-test/{binary}.xml";
+  let _ = fs::remove_file("target/junit-test/junit.xml");
+  ignored_command("junit_case_")
+    .env("ASSAY_JUNIT", report)
+    .output()
+    .unwrap();
+  let xml = fs::read_to_string("target/junit-test/junit.xml").unwrap();
+
+  let compare = [
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n",
+    "  <testsuite name=\"junit\" tests=\"3\" failures=\"1\" time=\"",
+    "    <testcase name=\"junit_case_fails\" classname=\"junit\" time=\"",
+    "      <failure message=\"&lt;broken&gt; &amp; &quot;quoted&quot;\">",
+    "    <testcase name=\"junit_case_passes\" classname=\"junit\" time=\"",
+    "    <testcase name=\"junit_case_should_panic\" classname=\"junit\" time=\"",
+    "</failure>\n    </testcase>\n",
+    "  </testsuite>\n</testsuites>\n",
+  ];
+  for line in compare {
+    if !xml.contains(line) {
+      panic!("Unexpected JUnit report.\n\nReport:\n{xml}");
+    }
+  }
+  assert_eq!(xml.matches("<testcase ").count(), 3);
+  assert_eq!(xml.matches("<failure ").count(), 1);
+  assert!(!xml.contains('\x1b'));
+}
+
+#[test]
+fn junit_report_only_when_asked() {
+  // Kept apart from `junit_report`'s run so the two can't overwrite each other
+  let report = "target/junit-unset/{binary}.xml";
+  let _ = fs::remove_dir_all("target/junit-unset");
+  ignored_command("junit_case_")
+    .env_remove("ASSAY_JUNIT")
+    .output()
+    .unwrap();
+  assert!(!std::path::Path::new("target/junit-unset").exists());
+
+  ignored_command("junit_case_passes")
+    .env("ASSAY_JUNIT", report)
+    .output()
+    .unwrap();
+  assert!(std::path::Path::new("target/junit-unset/junit.xml").exists());
+}
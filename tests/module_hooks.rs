@@ -0,0 +1,82 @@
+use assay::{assay, testing::run_ignored};
+use std::{fs, path::PathBuf};
+
+fn __assay_setup() {
+  fs::write("module_setup", "ran").unwrap();
+}
+
+fn __assay_teardown() {
+  fs::remove_file("module_setup").unwrap();
+}
+
+#[assay]
+fn module_setup_runs_before_each_test() {
+  assert_eq!(fs::read_to_string("module_setup")?, "ran");
+}
+
+#[assay(setup = fs::write("test_setup", "ran")?)]
+fn module_setup_runs_before_test_setup() {
+  assert!(PathBuf::from("module_setup").exists());
+  assert!(PathBuf::from("test_setup").exists());
+}
+
+mod without_hooks {
+  use assay::assay;
+  use std::path::PathBuf;
+
+  #[assay]
+  fn parent_hooks_are_not_used() {
+    assert!(!PathBuf::from("module_setup").exists());
+  }
+}
+
+mod fallible_hooks {
+  use assay::{assay, eyre::bail};
+
+  fn __assay_setup() -> assay::Result<()> {
+    std::fs::write("fallible_setup", "ran")?;
+    Ok(())
+  }
+
+  fn __assay_teardown() -> assay::Result<()> {
+    if std::path::Path::new("fail_teardown").exists() {
+      bail!("module teardown ran");
+    }
+    Ok(())
+  }
+
+  #[assay]
+  fn fallible_setup_runs() {
+    assert_eq!(std::fs::read_to_string("fallible_setup")?, "ran");
+  }
+
+  #[assay(ignore)]
+  fn module_teardown_error() {
+    std::fs::write("fail_teardown", "")?;
+  }
+}
+
+#[test]
+fn module_teardown_error_output() {
+  let output = run_ignored("module_teardown_error");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- fallible_hooks::module_teardown_error stdout ----",
+    "Error: module teardown ran",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for module hooks.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+mod glob_of_parent {
+  use super::*;
+
+  #[assay]
+  fn parent_hooks_imported_with_glob() {
+    assert!(PathBuf::from("module_setup").exists());
+  }
+}
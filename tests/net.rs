@@ -1,6 +1,10 @@
 use assay::assay;
+use assay::net::{mock_server, Reply};
+use std::io::{Read, Write};
 use std::net::IpAddr;
+use std::net::Shutdown;
 use std::net::TcpListener;
+use std::net::TcpStream;
 use std::net::UdpSocket;
 
 #[assay]
@@ -36,3 +40,48 @@ fn udp_addr() {
   );
   assert!(ipv6_addr.port() > 0);
 }
+
+#[assay]
+fn mock_server_scripted_replies() {
+  let server = mock_server([
+    Reply::Push(b"hello\n".to_vec()),
+    Reply::Respond(b"pong\n".to_vec()),
+    Reply::Echo,
+  ])?;
+  let mut client = TcpStream::connect(server.addr())?;
+  let mut buf = [0; 16];
+
+  let n = client.read(&mut buf)?;
+  assert_eq!(&buf[..n], b"hello\n");
+
+  client.write_all(b"ping\n")?;
+  let n = client.read(&mut buf)?;
+  assert_eq!(&buf[..n], b"pong\n");
+
+  client.write_all(b"echo me")?;
+  let n = client.read(&mut buf)?;
+  assert_eq!(&buf[..n], b"echo me");
+
+  // The script is done so the server closes its side
+  assert_eq!(client.read(&mut buf)?, 0);
+  client.shutdown(Shutdown::Write)?;
+  assert_eq!(server.join()?, b"ping\necho me");
+}
+
+#[assay]
+fn mock_server_client_hangs_up_early() {
+  let server = mock_server([Reply::Echo, Reply::Echo])?;
+  let mut client = TcpStream::connect(server.addr())?;
+  client.write_all(b"only once")?;
+  let mut buf = [0; 16];
+  let n = client.read(&mut buf)?;
+  assert_eq!(&buf[..n], b"only once");
+  drop(client);
+  assert_eq!(server.join()?, b"only once");
+}
+
+#[assay]
+fn mock_server_stop_without_client() {
+  let server = mock_server([Reply::Echo])?;
+  assert!(server.stop()?.is_empty());
+}
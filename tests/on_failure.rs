@@ -0,0 +1,90 @@
+use assay::{assay, testing::run_ignored};
+
+fn dump_diagnostics(output: &str) {
+  println!(
+    "diagnostics: the test printed {} lines",
+    output.lines().count()
+  );
+}
+
+fn must_not_run(_: &str) {
+  panic!("on_failure ran for a passing test");
+}
+
+#[assay(on_failure = must_not_run)]
+fn hook_skipped_on_success() {}
+
+#[assay(on_failure = must_not_run, should_panic)]
+fn hook_skipped_on_expected_panic() {
+  panic!("expected");
+}
+
+#[assay(ignore, on_failure = dump_diagnostics)]
+fn hook_runs_on_failure() {
+  println!("some state");
+  panic!("broken");
+}
+
+#[assay(ignore, on_failure = |output: &str| println!("closure saw: {}", output.contains("oops")))]
+fn hook_closure_runs_on_failure() {
+  assay::eyre::bail!("oops");
+}
+
+#[test]
+fn hook_runs_on_failure_output() {
+  let output = run_ignored("runs_on_failure");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- hook_runs_on_failure stdout ----",
+    "diagnostics: the test printed",
+    "broken",
+    "---- hook_closure_runs_on_failure stdout ----",
+    "closure saw: true",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for on_failure.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+fn check_output(stdout: &str, stderr: &str) -> assay::Result<()> {
+  assert!(stdout.contains("test result: ok. 1 passed"));
+  assert!(stdout.contains("printed to stdout"));
+  assert!(stderr.contains("printed to stderr"));
+  Ok(())
+}
+
+#[assay(inspect_output = check_output)]
+fn check_inspected_output() {
+  println!("printed to stdout");
+  eprintln!("printed to stderr");
+}
+
+#[assay(ignore, inspect_output = |stdout: &str, _: &str| {
+  if stdout.contains("secret") {
+    assay::eyre::bail!("output leaked the secret");
+  }
+  Ok(())
+})]
+fn inspected_output_rejected() {
+  println!("the secret is 42");
+}
+
+#[test]
+fn inspected_output_rejected_output() {
+  let output = run_ignored("inspected_output_rejected");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- inspected_output_rejected stdout ----",
+    "Error: inspect_output failed",
+    "output leaked the secret",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for inspect_output.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,188 @@
+use assay::{assay, assert_all, assert_no_panic, eyre::WrapErr, testing::run_ignored};
+use std::process::Command;
+
+#[assay]
+fn no_panic_returns_value() {
+  let value = assert_no_panic!(|| 2 + 2);
+  assert_eq!(value, 4);
+
+  let mut seen = Vec::new();
+  for i in 0..3 {
+    assert_no_panic!(|| seen.push(i));
+  }
+  assert_eq!(seen, [0, 1, 2]);
+}
+
+#[assay(ignore)]
+fn no_panic_reports_panic() {
+  let items: Vec<u8> = Vec::new();
+  assert_no_panic!(|| items[3]);
+}
+
+#[test]
+fn no_panic_reports_panic_output() {
+  let output = run_ignored("no_panic_reports_panic");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- no_panic_reports_panic stdout ----",
+    "assertion failed: closure panicked",
+    "panicked at tests/panics.rs:19:28:",
+    "index out of bounds: the len is 0 but the index is 3",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for panics.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn all_assertions_pass() {
+  let (a, b) = (1, "two");
+  assert_all! {
+    assert_eq!(a, 1);
+    assert_eq!(b, "two");
+    assert!(a < 2)
+  }
+}
+
+#[assay(ignore)]
+fn every_failure_reported() {
+  let (a, b, c) = (1, 2, 3);
+  assert_all! {
+    assert_eq!(a, 5);
+    assert_eq!(b, 2);
+    assert!(c > 10, "c was only {c}");
+  }
+}
+
+#[test]
+fn every_failure_reported_output() {
+  let output = run_ignored("every_failure_reported");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- every_failure_reported stdout ----",
+    "assertion failed: 2 of 3 assertions failed",
+    "panicked at tests/panics.rs:54:5:\nassertion failed: `(left == right)`",
+    "panicked at tests/panics.rs:56:5:\nc was only 3",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for panics.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(should_panic)]
+fn error_counts_as_panic() {
+  std::fs::read_to_string("missing.txt").wrap_err("cannot read `missing.txt`")?;
+}
+
+#[assay(ignore, should_panic)]
+fn error_as_panic_message() {
+  std::fs::read_to_string("missing.txt").wrap_err("cannot read `missing.txt`")?;
+}
+
+#[test]
+fn error_as_panic_message_output() {
+  // Running the child directly shows the panic message, which is otherwise
+  // hidden as the test passes
+  let output = Command::new(std::env::var("CARGO").unwrap())
+    .args([
+      "test",
+      "--test",
+      "panics",
+      "--",
+      "--ignored",
+      "--exact",
+      "error_as_panic_message",
+      "--nocapture",
+    ])
+    .env("ASSAY_SPLIT", "1")
+    .output()
+    .unwrap();
+  let stderr = String::from_utf8(output.stderr).unwrap();
+
+  assert!(output.status.success(), "{stderr}");
+  let compare = [
+    "panicked at tests/panics.rs:",
+    "cannot read `missing.txt`\n",
+  ];
+  for line in compare {
+    if !stderr.contains(line) {
+      panic!("Unexpected output for errors as panics.\n\nOutput:\n{stderr}");
+    }
+  }
+  assert!(!stderr.contains("called `Result::unwrap()`"));
+}
+
+#[assay(should_panic = "cannot read `missing.txt`")]
+fn error_matches_expected_panic() {
+  std::fs::read_to_string("missing.txt").wrap_err("cannot read `missing.txt`")?;
+}
+
+#[assay(ignore, should_panic = "index out of bounds")]
+fn expected_panic_mismatch() {
+  panic!("something else went wrong");
+}
+
+#[assay(ignore, should_panic = "index out of bounds")]
+fn expected_panic_missing() {}
+
+#[test]
+fn expected_panic_output() {
+  let output = run_ignored("expected_panic_mis");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "test expected_panic_mismatch - should panic ... FAILED",
+    "test expected_panic_missing - should panic ... FAILED",
+    "something else went wrong",
+    "panic did not contain expected string",
+    " expected substring: \"index out of bounds\"",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for expected panics.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(ignore)]
+fn no_panic_in_parallel() {
+  // Start them together so the calls overlap
+  let start = std::sync::Arc::new(std::sync::Barrier::new(8));
+  let threads = (0..8)
+    .map(|_| {
+      let start = start.clone();
+      std::thread::spawn(move || {
+        start.wait();
+        for i in 0..100_000 {
+          assert_no_panic!(|| i * 2);
+        }
+      })
+    })
+    .collect::<Vec<_>>();
+  for thread in threads {
+    thread.join().unwrap();
+  }
+  panic!("still reported after assert_no_panic");
+}
+
+#[test]
+fn no_panic_in_parallel_output() {
+  let output = run_ignored("no_panic_in_parallel");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- no_panic_in_parallel stdout ----",
+    "still reported after assert_no_panic",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for panics.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -1,5 +1,5 @@
 use assay::assay;
-use std::process::Command;
+use assay::testing::run_ignored;
 
 #[assay(ignore)]
 fn assert_eq() {
@@ -16,12 +16,14 @@ fn assert_eq_sorted() {
   assert_eq_sorted!([1, 3, 2], [1, 2, 4]);
 }
 
+#[assay(ignore)]
+fn sorted_empty_mismatch() {
+  assert_eq_sorted!(Vec::<i32>::new(), vec![1]);
+}
+
 #[test]
 fn pretty_assertions() {
-  let output = Command::new("cargo")
-    .args(["test", "--workspace", "--", "--ignored", "assert"])
-    .output()
-    .unwrap();
+  let output = run_ignored("assert");
   let assert_tests = String::from_utf8(output.stdout).unwrap();
 
   if !assert_tests.contains(
@@ -61,7 +63,27 @@ Both sides:
     assert_eq_sorted
     assert_ne
 
-test result: FAILED. 0 passed; 3 failed; 0 ignored; 0 measured; 1 filtered out",
+test result: FAILED. 0 passed; 3 failed; 0 ignored; 0 measured; 3 filtered out",
+  ) {
+    panic!(
+      "Unexpected output for assertions.\n\nOutput:\n{}",
+      assert_tests
+    );
+  }
+}
+
+#[test]
+fn sorted_empty_mismatch_output() {
+  let output = run_ignored("sorted_empty_mismatch");
+  let assert_tests = String::from_utf8(output.stdout).unwrap();
+
+  if !assert_tests.contains(
+    "assertion failed: `(left == right)`
+
+left: <empty Vec<i32>>
+right (Vec<i32>): [
+    1,
+]",
   ) {
     panic!(
       "Unexpected output for assertions.\n\nOutput:\n{}",
@@ -0,0 +1,94 @@
+#![cfg(unix)]
+
+use assay::{assay, assert_terminated, testing::run_ignored};
+use std::{fs, process::Command};
+
+#[assay]
+fn terminated_cleanly() {
+  let mut child = Command::new("sleep").arg("0.1").spawn()?;
+  let status = assert_terminated!(child, within = "2s");
+  assert_eq!(status.code(), Some(0));
+}
+
+#[assay(ignore)]
+fn terminated_too_late() {
+  let mut child = Command::new("sleep").arg("10").spawn()?;
+  assert_terminated!(child, within = "200ms");
+}
+
+#[assay(ignore)]
+fn terminated_with_failure() {
+  let mut child = Command::new("false").spawn()?;
+  assert_terminated!(child, within = "2s");
+}
+
+#[test]
+fn terminated_output() {
+  let output = run_ignored("terminated_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- terminated_too_late stdout ----",
+    "did not exit within 200ms and was killed",
+    "---- terminated_with_failure stdout ----",
+    "did not exit cleanly",
+    "  status: exit status: 1",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for process assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(rlimit_nofile = 64)]
+fn open_files_limited() {
+  use std::fs::File;
+
+  fs::write("file", "")?;
+  let mut open = Vec::new();
+  let error = loop {
+    match File::open("file") {
+      Ok(file) => open.push(file),
+      Err(e) => break e,
+    }
+    assert!(open.len() < 64, "opened more files than the limit allows");
+  };
+  // Some of the limit goes to stdio and whatever else the process had open
+  assert!(open.len() > 32);
+  // `EMFILE` on both Linux and macOS
+  assert_eq!(error.raw_os_error(), Some(24));
+}
+
+/// Allocate `mib` MiB and write to all of it so that it's actually resident
+fn touch_memory(mib: usize) -> usize {
+  let memory = vec![1u8; mib << 20];
+  memory.iter().map(|&b| b as usize).sum()
+}
+
+#[assay(max_memory = "256MiB")]
+fn memory_under_limit() {
+  assert_eq!(touch_memory(16), 16 << 20);
+}
+
+#[assay(ignore, max_memory = "32MiB")]
+fn memory_over_limit() {
+  assert_eq!(touch_memory(64), 64 << 20);
+}
+
+#[test]
+fn memory_over_limit_output() {
+  let output = run_ignored("memory_over_limit");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- memory_over_limit stdout ----",
+    "assertion failed: test used more memory than `max_memory` allows",
+    " maximum: 32.0 MiB",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for memory limits.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "regex")]
+
+use assay::{assay, redact, sandbox_dir};
+
+#[assay]
+fn redact_builtin_rules() {
+  let dir = sandbox_dir().unwrap();
+  let output = format!(
+    "wrote {}/out.json at 2024-03-01T12:30:45.123Z",
+    dir.display()
+  );
+  assert_eq!(redact(output, &[]), "wrote <TMPDIR>/out.json at <TIME>");
+}
+
+#[assay]
+fn redact_custom_rules() {
+  let output = "request 7f3c2a1e took 15ms, request 00ab99ff took 3ms";
+  assert_eq!(
+    redact(
+      output,
+      &[(r"[0-9a-f]{8}", "<ID>"), (r"\d+ms", "<DURATION>")]
+    ),
+    "request <ID> took <DURATION>, request <ID> took <DURATION>"
+  );
+}
+
+#[assay]
+fn redact_custom_rules_with_groups() {
+  let output = "user=alice id=1234";
+  assert_eq!(
+    redact(output, &[(r"id=(\d)\d+", "id=${1}…")]),
+    "user=alice id=1…"
+  );
+}
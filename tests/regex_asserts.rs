@@ -0,0 +1,44 @@
+use assay::{assay, assert_regex_captures, assert_regex_match, testing::run_ignored};
+
+#[assay]
+fn regex_matches() {
+  assert_regex_match!("2024-03-01 INFO started", r"^\d{4}-\d{2}-\d{2} INFO");
+  assert_regex_match!(String::from("took 15ms"), r"\d+ms");
+}
+
+#[assay]
+fn regex_captures_groups() {
+  let captures = assert_regex_captures!("user=alice id=1234", r"user=(\w+) id=(\d+)( admin)?");
+  assert_eq!(captures[0].as_deref(), Some("user=alice id=1234"));
+  assert_eq!(captures[1].as_deref(), Some("alice"));
+  assert_eq!(captures[2].as_deref(), Some("1234"));
+  assert_eq!(captures[3], None);
+}
+
+#[assay(ignore)]
+fn regex_no_match_reported() {
+  assert_regex_match!("started at noon", r"^\d{4}-\d{2}-\d{2}");
+}
+
+#[assay(ignore)]
+fn regex_invalid_pattern_reported() {
+  assert_regex_captures!("anything", r"(unclosed");
+}
+
+#[test]
+fn regex_failures_output() {
+  let output = run_ignored("regex_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- regex_no_match_reported stdout ----",
+    "assertion failed: text doesn't match `^\\d{4}-\\d{2}-\\d{2}`\n\n  text: \"started at noon\"",
+    "---- regex_invalid_pattern_reported stdout ----",
+    "assertion failed: invalid pattern `(unclosed`",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for regex assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
@@ -0,0 +1,88 @@
+use assay::{assay, testing::cargo_test};
+
+fn missing_capability() -> bool {
+  false
+}
+
+fn has_capability() -> bool {
+  true
+}
+
+#[assay(require = missing_capability)]
+fn skipped_without_capability() {
+  panic!("this should have been skipped");
+}
+
+#[assay(require = has_capability, env = [("PROBED", "yes")])]
+fn runs_with_capability() {
+  assert_eq!(std::env::var("PROBED")?, "yes");
+}
+
+#[assay(require = || true)]
+fn require_closure() {
+  assert!(assay::sandbox_dir().is_some());
+}
+
+#[assay(skip_on = ["linux", "macos", "windows"])]
+fn skipped_on_platform() {
+  if matches!(std::env::consts::OS, "linux" | "macos" | "windows") {
+    panic!("this should have been skipped");
+  }
+}
+
+#[assay(skip_on = "not-a-real-os", env = [("PROBED", "yes")])]
+fn runs_on_other_platforms() {
+  assert_eq!(std::env::var("PROBED")?, "yes");
+}
+
+#[test]
+fn skip_message_output() {
+  let output = cargo_test()
+    .args([
+      "--test",
+      "require",
+      "--",
+      "--exact",
+      "skipped_without_capability",
+    ])
+    .output()
+    .unwrap();
+  let tests =
+    String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+
+  // Shown without `--show-output` even though the test passed
+  let compare = [
+    "test skipped_without_capability ... ok",
+    "skipping skipped_without_capability: `missing_capability` returned false",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for skipped tests.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+#[test]
+fn skip_on_output() {
+  let output = cargo_test()
+    .args(["--test", "require", "--", "--exact", "skipped_on_platform"])
+    .output()
+    .unwrap();
+  let tests =
+    String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+
+  // Shown without `--show-output` even though the test passed
+  let compare = [
+    "test skipped_on_platform ... ok".to_string(),
+    format!(
+      "skipping skipped_on_platform: skipped on {}",
+      std::env::consts::OS
+    ),
+  ];
+  for line in compare {
+    if !tests.contains(&line) {
+      panic!("Unexpected output for skipped tests.\n\nOutput:\n{tests}");
+    }
+  }
+}
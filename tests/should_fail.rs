@@ -13,8 +13,8 @@
 //! tested without failing cargo test. These tests are all ignored by default
 //! and must be explicitly called for if we want them to run.
 
-use assay::assay;
-use std::process::Command;
+use assay::testing::{ignored_command, run_ignored};
+use assay::{assay, assert_in_sandbox, assert_within_dir};
 
 #[assay(ignore)]
 fn should_panic_and_cause_a_failure_case() {
@@ -26,16 +26,7 @@ fn should_not_panic_and_cause_a_failure_case() {}
 
 #[test]
 fn panics_in_macros() {
-  let output = Command::new("cargo")
-    .args([
-      "test",
-      "--workspace",
-      "--",
-      "--ignored",
-      "panic_and_cause_a_failure_case",
-    ])
-    .output()
-    .unwrap();
+  let output = run_ignored("panic_and_cause_a_failure_case");
   let tests = String::from_utf8(output.stdout).unwrap();
 
   if !tests.contains(
@@ -50,3 +41,176 @@ note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace",
     panic!("Unexpected output for panics.\n\nOutput:\n{}", tests);
   }
 }
+
+#[assay(ignore)]
+fn cwd_outside_sandbox() {
+  std::env::set_current_dir(std::env::temp_dir())?;
+  assert_in_sandbox!();
+}
+
+#[test]
+fn cwd_outside_sandbox_output() {
+  let output = run_ignored("cwd_outside_sandbox");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- cwd_outside_sandbox stdout ----",
+    "assertion failed: working directory is outside of the sandbox",
+    "      cwd: ",
+    "  sandbox: ",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for sandbox assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[test]
+fn cwd_outside_sandbox_harness_flags() {
+  // Flags that would change the child's output are dropped and `--no-capture`
+  // is passed along, with the failure then coming from the child's stderr
+  let output = ignored_command("cwd_outside_sandbox")
+    .args([
+      "--no-capture",
+      "--color",
+      "always",
+      "--format=terse",
+      "--test-threads",
+      "1",
+    ])
+    .output()
+    .unwrap();
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  assert!(!output.status.success());
+  if !tests.contains("assertion failed: working directory is outside of the sandbox") {
+    panic!("Unexpected output with harness flags.\n\nOutput:\n{tests}");
+  }
+}
+
+#[assay(ignore)]
+fn path_escapes_base_dir() {
+  std::fs::create_dir("base")?;
+  assert_within_dir!("base", "base/../escape");
+}
+
+#[test]
+fn path_escapes_base_dir_output() {
+  let output = run_ignored("path_escapes_base_dir");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- path_escapes_base_dir stdout ----",
+    "assertion failed: `base/../escape` is outside of `base`",
+    "/escape\n  base: ",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for within dir assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(ignore, include = ["Cargo.toml", ("README.md", "Cargo.toml")], include_no_overwrite)]
+fn include_collision() {}
+
+#[test]
+fn include_collision_output() {
+  let output = run_ignored("include_collision");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- include_collision stdout ----",
+    "README.md` as `Cargo.toml`: destination already exists",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for include collisions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(ignore)]
+fn exits_without_result() {
+  std::process::exit(3);
+}
+
+#[test]
+fn exits_without_result_output() {
+  let output = run_ignored("exits_without_result");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "test exits_without_result ... FAILED",
+    "test process exited without a result (exit status: 3)",
+    "test exits_without_result ...",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for a child that exits early.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(ignore, include = ["Cargo.toml"], link = ["Cargo.toml"])]
+fn link_over_included_file() {}
+
+#[test]
+fn link_over_included_file_output() {
+  let output = run_ignored("link_over_included_file");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- link_over_included_file stdout ----",
+    "Cargo.toml` as `Cargo.toml`: destination already exists",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for link collisions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(ignore, include_dir = ["tests/fixtures/missing"])]
+fn include_dir_problem_missing() {}
+
+#[assay(ignore, include_dir = ["Cargo.toml"])]
+fn include_dir_problem_file() {}
+
+#[test]
+fn include_dir_problem_output() {
+  let output = run_ignored("include_dir_problem_");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- include_dir_problem_missing stdout ----",
+    "tests/fixtures/missing`: directory not found",
+    "---- include_dir_problem_file stdout ----",
+    "Cargo.toml`: not a directory",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for directory includes.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay(ignore, include = ["tests/fixtures/*.missing"])]
+fn include_glob_without_matches() {}
+
+#[test]
+fn include_glob_without_matches_output() {
+  let output = run_ignored("include_glob_without_matches");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- include_glob_without_matches stdout ----",
+    "tests/fixtures/*.missing`: file not found",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for include globs.\n\nOutput:\n{tests}");
+    }
+  }
+}
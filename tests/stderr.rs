@@ -0,0 +1,40 @@
+use assay::{assay, testing::run_ignored};
+
+#[assay(forbid_stderr)]
+fn quiet_stderr() {
+  println!("stdout is fine");
+}
+
+#[assay(forbid_stderr = ["deprecated"])]
+fn allowed_stderr() {
+  eprintln!("warning: `old_api` is deprecated");
+}
+
+#[assay(ignore, forbid_stderr)]
+fn noisy_stderr() {
+  eprintln!("stray log line");
+}
+
+#[assay(ignore, forbid_stderr)]
+fn noisy_stderr_panic() {
+  panic!("failed while forbidding stderr");
+}
+
+#[test]
+fn noisy_stderr_output() {
+  let output = run_ignored("noisy_stderr");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- noisy_stderr stdout ----",
+    "assertion failed: test wrote to stderr with `forbid_stderr` set",
+    "stray log line",
+    "---- noisy_stderr_panic stdout ----",
+    "failed while forbidding stderr",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for forbid_stderr.\n\nOutput:\n{tests}");
+    }
+  }
+}
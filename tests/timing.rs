@@ -0,0 +1,209 @@
+use assay::{
+  assay, assert_duration_within, assert_throughput, clock, testing::run_ignored, MockTime,
+  TimeSource,
+};
+use std::{
+  thread,
+  time::{Duration, Instant},
+};
+
+#[assay]
+fn duration_within_tolerance() {
+  let value = assert_duration_within!(Duration::from_millis(100), Duration::from_millis(90), {
+    thread::sleep(Duration::from_millis(100));
+    5
+  });
+  assert_eq!(value, 5);
+}
+
+#[assay(ignore)]
+fn duration_outside_tolerance() {
+  assert_duration_within!(Duration::from_secs(1), Duration::from_millis(100), {});
+}
+
+#[test]
+fn duration_outside_tolerance_output() {
+  let output = run_ignored("duration_outside_tolerance");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- duration_outside_tolerance stdout ----",
+    "assertion failed: `elapsed` is not within `target ± tolerance`",
+    "   target: 1s",
+    "tolerance: 100ms",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for timing assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn throughput_above_minimum() {
+  let mut total = 0u64;
+  let rate = assert_throughput!(1000, 10_000, || total += 1);
+  assert!(rate >= 1000.0);
+  assert_eq!(total, 10_000);
+}
+
+#[assay(ignore)]
+fn throughput_below_minimum() {
+  assert_throughput!(1000.0, 5, || thread::sleep(Duration::from_millis(10)));
+}
+
+#[test]
+fn throughput_below_minimum_output() {
+  let output = run_ignored("throughput_below_minimum");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- throughput_below_minimum stdout ----",
+    "assertion failed: throughput is below the minimum",
+    "  minimum: 1000 ops/sec",
+    "    calls: 5 in ",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for throughput assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+struct Session {
+  created: Instant,
+  ttl: Duration,
+}
+
+impl Session {
+  fn new(ttl: Duration) -> Self {
+    Self {
+      created: clock().now(),
+      ttl,
+    }
+  }
+
+  fn expired(&self) -> bool {
+    clock().now().duration_since(self.created) >= self.ttl
+  }
+}
+
+#[assay(mock_time)]
+fn mock_time_advance() {
+  assert!(clock().is_mocked());
+  let start = clock().now();
+  thread::sleep(Duration::from_millis(10));
+  assert_eq!(clock().now(), start);
+
+  clock().advance(Duration::from_secs(90));
+  assert_eq!(clock().now() - start, Duration::from_secs(90));
+}
+
+#[assay(mock_time)]
+fn mock_time_across_threads() {
+  let start = clock().now();
+  thread::spawn(|| clock().advance(Duration::from_secs(10)))
+    .join()
+    .unwrap();
+  assert_eq!(clock().now() - start, Duration::from_secs(10));
+  assert!(thread::spawn(|| clock().is_mocked()).join().unwrap());
+}
+
+#[assay(mock_time)]
+fn mock_time_session_expiry() {
+  let session = Session::new(Duration::from_secs(30 * 60));
+  assert!(!session.expired());
+  clock().advance(Duration::from_secs(29 * 60));
+  assert!(!session.expired());
+  clock().advance(Duration::from_secs(60));
+  assert!(session.expired());
+}
+
+#[assay(mock_time)]
+fn mock_time_set() {
+  let start = assay::now();
+  clock().set(Duration::from_secs(5));
+  assert_eq!(assay::now() - start, Duration::from_secs(5));
+  clock().set(Duration::from_secs(2));
+  assert_eq!(assay::now() - start, Duration::from_secs(2));
+}
+
+struct RateLimiter<T> {
+  time: T,
+  window: Duration,
+  last: Option<Instant>,
+}
+
+impl<T: TimeSource> RateLimiter<T> {
+  fn allow(&mut self) -> bool {
+    let now = self.time.now();
+    match self.last {
+      Some(last) if now.saturating_duration_since(last) < self.window => false,
+      _ => {
+        self.last = Some(now);
+        true
+      }
+    }
+  }
+}
+
+#[assay]
+fn mock_time_source() {
+  let time = MockTime::new();
+  let mut limiter = RateLimiter {
+    time: time.clone(),
+    window: Duration::from_secs(1),
+    last: None,
+  };
+  assert!(limiter.allow());
+  assert!(!limiter.allow());
+  time.advance(Duration::from_millis(999));
+  assert!(!limiter.allow());
+  time.advance(Duration::from_millis(1));
+  assert!(limiter.allow());
+
+  let start = time.now();
+  time.set(Duration::ZERO);
+  assert_eq!(start - time.now(), Duration::from_secs(1));
+  assert!(!limiter.allow());
+}
+
+#[assay]
+fn mock_time_source_across_threads() {
+  let time = MockTime::new();
+  let start = time.now();
+  let moved = time.clone();
+  thread::spawn(move || moved.advance(Duration::from_secs(60)))
+    .join()
+    .unwrap();
+  assert_eq!(time.now() - start, Duration::from_secs(60));
+}
+
+#[assay]
+fn clock_as_time_source() {
+  fn read(source: &impl TimeSource) -> Instant {
+    source.now()
+  }
+  let start = read(&clock());
+  thread::sleep(Duration::from_millis(10));
+  assert!(read(&clock()) > start);
+  assert!(assay::now() > start);
+}
+
+#[assay]
+fn real_time_by_default() {
+  assert!(!clock().is_mocked());
+  let start = clock().now();
+  thread::sleep(Duration::from_millis(10));
+  assert!(clock().now() > start);
+}
+
+#[cfg(feature = "async-tokio-runtime")]
+#[assay(mock_time)]
+async fn mock_time_tokio_sleep() {
+  let wall = Instant::now();
+  let start = tokio::time::Instant::now();
+  tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+  assert!(start.elapsed() >= Duration::from_secs(60 * 60));
+  assert!(wall.elapsed() < Duration::from_secs(60));
+}
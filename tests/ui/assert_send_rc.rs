@@ -0,0 +1,3 @@
+fn main() {
+  assay::assert_send!(std::rc::Rc<u8>);
+}
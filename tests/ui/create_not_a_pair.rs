@@ -0,0 +1,6 @@
+use assay::assay;
+
+#[assay(create = [("config.toml", "key = 'value'"), "data.json"])]
+fn not_a_pair() {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use assay::assay;
+
+#[assay(env = [("", "v")])]
+fn empty_key() {}
+
+fn main() {}
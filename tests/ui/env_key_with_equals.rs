@@ -0,0 +1,6 @@
+use assay::assay;
+
+#[assay(env = [("A=B", "v")])]
+fn key_with_equals() {}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use assay::assay;
+
+#[assay(env_remove = [])]
+fn nothing_removed() {}
+
+#[assay(env_remove = ["OK", "BAD=NAME"])]
+fn invalid_name() {}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use assay::{assay, TestFixture};
+
+struct Empty;
+
+impl TestFixture for Empty {
+  fn setup() -> assay::Result<Self> {
+    Ok(Empty)
+  }
+
+  fn teardown(self) -> assay::Result<()> {
+    Ok(())
+  }
+}
+
+#[assay(fixture = Empty)]
+fn both(empty: &mut Empty) {}
+
+#[assay]
+fn by_value(empty: Empty) {}
+
+fn main() {}
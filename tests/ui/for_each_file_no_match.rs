@@ -0,0 +1,6 @@
+use assay::assay;
+
+#[assay(for_each_file = "*.missing")]
+fn no_inputs() {}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use assay::assay;
+
+mod a {
+  pub struct Store;
+}
+
+mod b {
+  pub struct Store;
+}
+
+#[assay(impls = [a::Store, b::Store])]
+fn contract() {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use assay::assay;
+
+#[assay(inline, env = [("KEY", "value")])]
+fn inline_with_env() {}
+
+fn main() {}
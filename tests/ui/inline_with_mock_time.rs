@@ -0,0 +1,6 @@
+use assay::assay;
+
+#[assay(inline, mock_time)]
+fn inline_with_mock_time() {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use assay::assay;
+
+#[assay(flags = [verbose], name_separator = "-")]
+fn invalid_separator() {}
+
+fn main() {}
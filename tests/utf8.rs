@@ -0,0 +1,38 @@
+use assay::{assay, assert_valid_utf8, from_utf8_lossy_logged, testing::run_ignored};
+
+#[assay]
+fn valid_utf8() {
+  let bytes = "héllo wörld".as_bytes().to_vec();
+  let text: &str = assert_valid_utf8!(bytes);
+  assert_eq!(text, "héllo wörld");
+  assert_eq!(assert_valid_utf8!(b"plain ascii"), "plain ascii");
+}
+
+#[assay(ignore)]
+fn invalid_utf8_reported() {
+  assert_valid_utf8!(b"status: ok\n\xff\xfebody follows");
+}
+
+#[test]
+fn invalid_utf8_reported_output() {
+  let output = run_ignored("invalid_utf8_reported");
+  let tests = String::from_utf8(output.stdout).unwrap();
+
+  let compare = [
+    "---- invalid_utf8_reported stdout ----",
+    "assertion failed: bytes are not valid UTF-8",
+    "invalid sequence at byte 11: [ff]",
+    " context: \"status: ok\\n\u{fffd}\u{fffd}body follows\"",
+  ];
+  for line in compare {
+    if !tests.contains(line) {
+      panic!("Unexpected output for UTF-8 assertions.\n\nOutput:\n{tests}");
+    }
+  }
+}
+
+#[assay]
+fn lossy_decoding() {
+  assert_eq!(from_utf8_lossy_logged(b"valid"), "valid");
+  assert_eq!(from_utf8_lossy_logged(b"in\xffvalid"), "in\u{fffd}valid");
+}